@@ -1,22 +1,154 @@
+use rand::Rng;
 use winit::{
-    event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
+    dpi::PhysicalSize,
+    event::{
+        ElementState, Event, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta,
+        VirtualKeyCode, WindowEvent,
+    },
     event_loop::{ControlFlow, EventLoop},
+    window::Fullscreen,
 };
 
 use crate::{
+    brush::Brush,
+    chart,
+    cli::{Command, CommandLine},
     dispatch_manager::DispatchManager,
+    export,
+    gallery::Gallery,
+    golly,
+    hud::{CellReport, HudImage, SpeedMeter},
+    import::{self, ImageMapping},
     init,
-    options::{Options, PARAMETER_SPACE, WORLD_SIZE},
+    options::{Destrobe, Options, Pane, Slice, SliceAxis, PARAMETER_SPACE},
+    palette::{Color, Palette},
+    pattern::Pattern,
     presenter::Presenter,
     renderer::Renderer,
-    stats::{AutomaticJudgement, Judge, Scorer, Stats},
+    rle,
+    rule::{self, KernelArguments},
+    selection::Selection,
+    snapshot::WorldSnapshot,
+    stamps::{self, Stamp},
+    stats::{AutomaticJudgement, History, Judge, Scorer, Stats},
 };
-use std::sync::Arc;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+const MIN_ZOOM: f32 = 1.0 / 64.0;
+const MAX_ZOOM: f32 = 64.0;
+const KEY_ZOOM_STEP: f32 = 2.0;
+/// How much of the view the arrow keys pan by.
+const KEY_PAN_FRACTION: f32 = 0.125;
+/// How much one notch of the scroll wheel zooms by.
+const WHEEL_ZOOM_STEP: f32 = 1.25;
+/// Touchpads scroll by pixels rather than lines, this many count as one notch.
+const PIXELS_PER_LINE: f32 = 40.0;
+/// Measuring the world means going through every cell, so while the chart is hidden the history
+/// is only sampled this many generations apart, which is still enough to export.
+const HIDDEN_HISTORY_SPACING: u64 = 256;
+
+/// What dragging the mouse does.
+#[derive(Clone, Copy, PartialEq)]
+enum Tool {
+    /// Dragging with the left mouse button pans the view.
+    Navigate,
+    Paint,
+    Select,
+    Stamp,
+    /// The left mouse button picks the row under the mouse for the space-time pane and the right
+    /// one the column.
+    Slice,
+}
+
+/// A second world shown beside the main one with the same view, see `toggle_comparison`. It
+/// runs and resets along with the main world, but keeps its own rule and seed.
+struct Comparison {
+    renderer: Renderer,
+    kernel_arguments: KernelArguments,
+    seed: u32,
+    pane: Pane,
+    /// Where the cells which differ between the two worlds are shown, if they are.
+    difference_pane: Option<Pane>,
+    show_differences: bool,
+}
+
+impl Comparison {
+    /// The main options with this world's rule, seed and pane. Overlays are only drawn over the
+    /// main world.
+    fn options(&self, main: &Options, generation: u64) -> Options {
+        let mut options = Options {
+            kernel_arguments: self.kernel_arguments,
+            seed: self.seed,
+            pane: Some(self.pane),
+            difference_pane: self.difference_pane,
+            hud: false,
+            chart: false,
+            minimap: false,
+            ..main.clone()
+        };
+        if options.destrobe == Destrobe::Background {
+            options.background = rule::background_value(&self.kernel_arguments, generation);
+        }
+        options
+    }
+}
 
 struct AppData {
     options: Options,
     renderer: Renderer,
     dispatcher: DispatchManager,
+    output_dir: PathBuf,
+    image_mapping: ImageMapping,
+    /// How many generations have passed since the world was last reset.
+    generation: u64,
+    /// The generation of a snapshot which will be uploaded during the next render.
+    restored_generation: Option<u64>,
+    /// Where the save key writes snapshots to. When this is not set, each snapshot gets a new
+    /// file in the output directory.
+    save_target: Option<PathBuf>,
+    /// Where the screenshot keys write to. When this is not set, each screenshot gets a new file
+    /// in the output directory.
+    screenshot_target: Option<PathBuf>,
+    /// The snapshot the load key reads, which is whichever was last saved or loaded.
+    snapshot_file: Option<PathBuf>,
+    /// Physical size of the window's contents, which the presented image matches.
+    window_size: (u32, u32),
+    /// Physical position of the mouse inside the window, if it is inside the window.
+    cursor: Option<(f32, f32)>,
+    modifiers: ModifiersState,
+    /// Whether the view is being dragged around.
+    panning: bool,
+    tool: Tool,
+    brush: Brush,
+    /// The cell where the selection being dragged out started.
+    selection_anchor: Option<(i32, i32)>,
+    /// What was last copied or cut.
+    clipboard: Option<Pattern>,
+    stamp_dir: PathBuf,
+    /// The palette given with --palette, kept so that cycling through palettes can return to it.
+    command_line_palette: Palette,
+    /// The stamps saved for the current rule, loaded when stamp mode is entered.
+    stamps: Vec<Stamp>,
+    /// The rule `stamps` were loaded for.
+    stamps_rule: KernelArguments,
+    stamp_index: usize,
+    /// The name of a stamp being typed. Keys type instead of doing what they normally do until
+    /// it is confirmed or cancelled.
+    stamp_name: Option<String>,
+    speed_meter: SpeedMeter,
+    /// Measured whenever the speed meter updates, only while the HUD is shown.
+    population_density: f32,
+    /// Stats of the world since it was last reset, for the chart.
+    history: History,
+    comparison: Option<Comparison>,
+    /// Shown instead of the main world while it is open, which pauses meanwhile.
+    gallery: Option<Gallery>,
+    /// Whether the main world is reset when the gallery closes, since a reset while the gallery
+    /// is open, E.G. from changing the rule or seed, only reaches the thumbnails.
+    reset_after_gallery: bool,
 }
 
 pub struct App {
@@ -25,7 +157,19 @@ pub struct App {
 }
 
 impl App {
-    pub fn new() -> Self {
+    pub fn new(command_line: &CommandLine) -> Self {
+        let common = &command_line.common;
+        // Loaded first because it decides how big the world is.
+        let snapshot = common.load_world.as_ref().map(|filename| {
+            WorldSnapshot::load(filename).unwrap_or_else(|message| {
+                eprintln!("{}", message);
+                std::process::exit(1);
+            })
+        });
+        let world_size = snapshot
+            .as_ref()
+            .map(|snapshot| snapshot.world_size)
+            .unwrap_or(common.world_size);
         let init::InitResult {
             device,
             queue,
@@ -33,12 +177,20 @@ impl App {
             events_loop,
             swapchain,
             swapchain_images,
-        } = init::init();
+        } = init::init(
+            common.device,
+            command_line.command.is_interactive(),
+            common.present_mode,
+        );
+        let window_size = surface.window().inner_size();
+        let scale_factor = surface.window().scale_factor() as f32;
 
+        // Drawn at the window's physical size so that every pixel on screen is computed exactly
+        // once, and recreated whenever the window is resized.
         let presenter = Arc::new(Presenter::new(
             device.clone(),
             queue.clone(),
-            (1024, 1024),
+            (window_size.width.max(1), window_size.height.max(1)),
             swapchain.format(),
         ));
 
@@ -46,12 +198,8 @@ impl App {
             device.clone(),
             queue.clone(),
             presenter.get_presented_image(),
+            world_size,
         );
-        let mut kernel_arguments = [0i16; PARAMETER_SPACE];
-        kernel_arguments[0] = 2;
-        for (index, value) in std::env::args().skip(1).enumerate() {
-            kernel_arguments[index] = value.parse().unwrap();
-        }
 
         let dispatcher = DispatchManager::new(
             device,
@@ -62,16 +210,114 @@ impl App {
             &swapchain_images,
         );
 
-        Self {
-            events_loop,
-            data: AppData {
-                options: Options {
-                    kernel_arguments,
-                    ..Default::default()
-                },
-                renderer,
-                dispatcher,
+        let mut data = AppData {
+            options: Options {
+                kernel_arguments: command_line.command.rule(),
+                seed: common.seed,
+                soup: common.soup,
+                palette: common.palette.clone(),
+                scale_factor,
+                ..Default::default()
             },
+            renderer,
+            dispatcher,
+            output_dir: common.output_dir.clone(),
+            image_mapping: common.image_mapping,
+            generation: 0,
+            restored_generation: None,
+            save_target: common.save_world.clone(),
+            screenshot_target: common.screenshot.clone(),
+            snapshot_file: common.load_world.clone(),
+            window_size: (window_size.width, window_size.height),
+            cursor: None,
+            modifiers: ModifiersState::empty(),
+            panning: false,
+            tool: Tool::Navigate,
+            brush: Brush::default(),
+            selection_anchor: None,
+            clipboard: None,
+            stamp_dir: common.stamp_dir.clone(),
+            command_line_palette: common.palette.clone(),
+            stamps: Vec::new(),
+            stamps_rule: [0; PARAMETER_SPACE],
+            stamp_index: 0,
+            stamp_name: None,
+            speed_meter: SpeedMeter::new(),
+            population_density: 0.0,
+            history: History::new(),
+            comparison: None,
+            gallery: None,
+            reset_after_gallery: false,
+        };
+        if let Some(filename) = &common.load_image {
+            if !data.load_image(filename) {
+                std::process::exit(1);
+            }
+        }
+        if let Some(snapshot) = snapshot {
+            data.restore_snapshot(snapshot);
+        }
+        if let Some(filename) = &common.load_pattern {
+            if !data.load_pattern(filename) {
+                std::process::exit(1);
+            }
+        }
+        if let Command::View {
+            compare_rule,
+            compare_seed,
+            ..
+        } = &command_line.command
+        {
+            if compare_rule.is_some() || compare_seed.is_some() {
+                data.start_comparison(
+                    compare_rule
+                        .as_ref()
+                        .map_or(data.options.kernel_arguments, |rule| **rule),
+                    compare_seed.unwrap_or(data.options.seed),
+                    false,
+                );
+            }
+        }
+
+        Self { events_loop, data }
+    }
+
+    /// Runs one of the commands which do not open the viewer, then returns.
+    pub fn run_headless(
+        mut self,
+        command: Command,
+        save_world: Option<PathBuf>,
+        screenshot: Option<PathBuf>,
+    ) {
+        let data = &mut self.data;
+        match command {
+            Command::View { .. } => panic!("The viewer cannot be run headless."),
+            Command::Golly { .. } => panic!("Exporting a rule does not need an App."),
+            Command::Search { until, .. } => data.search(until),
+            Command::Score { .. } => {
+                data.reset_headless();
+                println!("{:?}", data.compute_score());
+            }
+            Command::Judge { .. } => {
+                // Known judgements are already printed as they are found.
+                let judgement = data.compute_judgement();
+                if judgement.is_unknown() {
+                    println!("{:?}", judgement);
+                }
+            }
+            Command::Render {
+                generations, out, ..
+            } => {
+                if !data.render_to_file(generations, &out) {
+                    std::process::exit(1);
+                }
+            }
+        }
+        if let Some(filename) = save_world {
+            data.save_snapshot(&filename);
+        }
+        if let Some(filename) = screenshot {
+            data.save_screenshot(&filename, true);
         }
     }
 
@@ -91,8 +337,35 @@ impl App {
                 event: WindowEvent::CursorMoved { position, .. },
                 ..
             } => {
-                data.set_offset(position.x as f32 / 1024.0, 1.0 - position.y as f32 / 1024.0);
+                let previous = data.cursor;
+                data.cursor = Some((position.x as f32, position.y as f32));
+                if data.panning {
+                    data.continue_panning(previous);
+                    return;
+                }
+                match data.tool {
+                    Tool::Navigate => (),
+                    Tool::Paint => data.continue_painting(),
+                    Tool::Select => data.continue_selecting(),
+                    Tool::Stamp | Tool::Slice => (),
+                }
             }
+            Event::WindowEvent {
+                event: WindowEvent::ModifiersChanged(modifiers),
+                ..
+            } => data.modifiers = modifiers,
+            Event::WindowEvent {
+                event: WindowEvent::CursorLeft { .. },
+                ..
+            } => data.cursor = None,
+            Event::WindowEvent {
+                event: WindowEvent::MouseInput { state, button, .. },
+                ..
+            } => data.on_mouse_button(button, state),
+            Event::WindowEvent {
+                event: WindowEvent::MouseWheel { delta, .. },
+                ..
+            } => data.on_mouse_wheel(delta),
             Event::WindowEvent {
                 event:
                     WindowEvent::KeyboardInput {
@@ -106,7 +379,7 @@ impl App {
                     },
                 ..
             } => match code {
-                VirtualKeyCode::Escape => *flow = ControlFlow::Exit,
+                VirtualKeyCode::Escape if data.stamp_name.is_none() => *flow = ControlFlow::Exit,
                 code => data.on_key(code),
             },
             Event::WindowEvent {
@@ -125,14 +398,38 @@ impl App {
                 _ => (),
             },
             Event::WindowEvent {
-                event: WindowEvent::Resized(_),
+                event: WindowEvent::ReceivedCharacter(c),
+                ..
+            } => data.type_character(c),
+            Event::WindowEvent {
+                event: WindowEvent::DroppedFile(path),
                 ..
-            } => data.dispatcher.invalidate_swapchain(),
+            } => {
+                match path.extension().and_then(|e| e.to_str()) {
+                    Some("m3w") => data.load_snapshot(&path),
+                    Some("rle") => data.load_pattern(&path),
+                    _ => data.load_image(&path),
+                };
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Resized(size),
+                ..
+            } => data.resize(size),
+            Event::WindowEvent {
+                event:
+                    WindowEvent::ScaleFactorChanged {
+                        scale_factor,
+                        new_inner_size,
+                    },
+                ..
+            } => {
+                data.options.scale_factor = scale_factor as f32;
+                data.resize(*new_inner_size);
+            }
             Event::MainEventsCleared => {
-                let success = data.render();
-                data.after_frame();
-                if !success {
-                    return;
+                // A reset or skip which did not make it into a frame is kept for the next one.
+                if data.render() {
+                    data.after_frame();
                 }
             }
             _ => (),
@@ -142,26 +439,439 @@ impl App {
 
 impl AppData {
     fn render(&mut self) -> bool {
-        self.renderer.render(&mut self.dispatcher, &self.options)
+        if let Some(gallery) = &mut self.gallery {
+            // The rule can still be changed with the usual keys while the gallery is open.
+            if *gallery.center() != self.options.kernel_arguments {
+                *gallery = Gallery::new(&self.renderer, self.options.kernel_arguments);
+            }
+            let submitted = gallery.render(&mut self.dispatcher, &self.options);
+            self.reset_after_gallery |= submitted && self.options.reset;
+            return submitted;
+        }
+        if self.options.hud || self.options.chart {
+            let mut image = HudImage::new();
+            let mut top = 0;
+            if self.options.hud {
+                top = image.text_panel(top, &self.hud_lines());
+            }
+            if self.options.chart {
+                chart::draw(&mut image, top, &self.history);
+            }
+            self.renderer.set_hud(&image.pixels);
+        }
+        let options = self.options.clone();
+        self.render_with(&options)
+    }
+
+    /// Everything which advances the world goes through here so that the generation count stays
+    /// correct. The count only moves when a frame was actually submitted, since nothing is
+    /// simulated otherwise.
+    fn render_with(&mut self, options: &Options) -> bool {
+        let mut generation = match self.restored_generation {
+            Some(generation) => generation,
+            None if options.reset => 0,
+            None => self.generation,
+        };
+        let generations = (options.rate + options.skip) as u64;
+        generation += generations;
+        let mut options = options.clone();
+        if options.destrobe == Destrobe::Background {
+            options.background = rule::background_value(&options.kernel_arguments, generation);
+        }
+        let submitted = match &mut self.comparison {
+            Some(comparison) if options.display => {
+                let other_options = comparison.options(&options, generation);
+                Renderer::render_together(
+                    &mut self.dispatcher,
+                    vec![
+                        (&mut self.renderer, &options),
+                        (&mut comparison.renderer, &other_options),
+                    ],
+                )
+            }
+            _ => self.renderer.render(&mut self.dispatcher, &options),
+        };
+        if submitted {
+            self.generation = generation;
+            self.restored_generation = None;
+            self.speed_meter.add_generations(generations);
+        }
+        submitted
+    }
+
+    /// Shows every rule one coefficient away from the current one instead of the world, or goes
+    /// back to the world.
+    fn toggle_gallery(&mut self) {
+        if self.gallery.take().is_some() {
+            println!("Gallery closed");
+            if self.reset_after_gallery {
+                self.reset_world();
+            }
+            self.reset_after_gallery = false;
+            return;
+        }
+        if rule::neighbors(&self.options.kernel_arguments).is_empty() {
+            println!("This rule has no neighboring rules.");
+            return;
+        }
+        println!("Click a rule to switch to it.");
+        self.gallery = Some(Gallery::new(&self.renderer, self.options.kernel_arguments));
+    }
+
+    /// Switches to the rule under the mouse and closes the gallery.
+    fn pick_from_gallery(&mut self, cursor: (f32, f32)) {
+        let (x, y) = self.window_to_image(cursor);
+        let picked = match &self.gallery {
+            Some(gallery) => gallery.rule_at(x, y),
+            None => return,
+        };
+        if let Some(kernel_arguments) = picked {
+            println!("Switched to {}", rule::notation(&kernel_arguments));
+            self.options.kernel_arguments = kernel_arguments;
+            self.gallery = None;
+            self.reset_after_gallery = false;
+            self.reset_world();
+        }
+    }
+
+    /// Shows a second world beside the main one, starting as a copy of it, or closes it.
+    fn toggle_comparison(&mut self) {
+        if self.comparison.take().is_some() {
+            self.update_panes();
+            println!("Comparison closed");
+        } else {
+            self.start_comparison(self.options.kernel_arguments, self.options.seed, true);
+        }
+    }
+
+    /// The second world keeps `kernel_arguments` and `seed` while the main world's rule and
+    /// seed are changed as usual. A world which is about to be uploaded into the main world, E.G.
+    /// one loaded at startup, is uploaded into both. Otherwise the main world is only copied when
+    /// `copy_world` is set, since before the first frame it has not been filled in yet and the
+    /// second world is better off being reset with its own rule and seed.
+    fn start_comparison(&mut self, kernel_arguments: KernelArguments, seed: u32, copy_world: bool) {
+        let mut renderer = self.renderer.new_beside(self.renderer.world_size());
+        renderer.compare_with(&self.renderer);
+        let uploaded = self
+            .renderer
+            .with_pending_upload(|world| renderer.upload_world(world))
+            .is_some();
+        if !uploaded && copy_world {
+            self.renderer
+                .with_cpu_world_buffer(|world| renderer.upload_world(world));
+        }
+        println!(
+            "Comparing with {} seed {}",
+            rule::notation(&kernel_arguments),
+            seed
+        );
+        self.comparison = Some(Comparison {
+            renderer,
+            kernel_arguments,
+            seed,
+            pane: Pane {
+                left: 0,
+                top: 0,
+                width: 0,
+                height: 0,
+            },
+            difference_pane: None,
+            show_differences: false,
+        });
+        self.update_panes();
+    }
+
+    fn toggle_differences(&mut self) {
+        match &mut self.comparison {
+            Some(comparison) => comparison.show_differences = !comparison.show_differences,
+            None => {
+                println!("Nothing to compare with, start a comparison first.");
+                return;
+            }
+        }
+        self.update_panes();
+    }
+
+    /// Splits the image evenly between the main world, the world it is compared with and the
+    /// differences between them.
+    fn update_panes(&mut self) {
+        let (width, height) = self.renderer.target_size();
+        let comparison = match &mut self.comparison {
+            Some(comparison) => comparison,
+            None => {
+                self.options.pane = None;
+                return;
+            }
+        };
+        let count = if comparison.show_differences { 3 } else { 2 };
+        let pane_width = width / count;
+        let pane = |index: u32| Pane {
+            left: index * pane_width,
+            top: 0,
+            // The last pane takes whatever is left over.
+            width: if index == count - 1 {
+                width - index * pane_width
+            } else {
+                pane_width
+            },
+            height,
+        };
+        self.options.pane = Some(pane(0));
+        comparison.pane = pane(1);
+        comparison.difference_pane = if comparison.show_differences {
+            Some(pane(2))
+        } else {
+            None
+        };
+    }
+
+    /// What the HUD shows: the rule, how fast it is running, the view, and the cell under the mouse.
+    fn hud_lines(&self) -> Vec<String> {
+        let meter = &self.speed_meter;
+        let mut lines = vec![
+            rule::notation(&self.options.kernel_arguments),
+            format!(
+                "GEN {}  {:.0} GEN/S  {:.0} FPS",
+                self.generation, meter.generations_per_second, meter.frames_per_second
+            ),
+            format!(
+                "DENSITY {:.4}  ZOOM {:.3}X",
+                self.population_density, self.options.zoom
+            ),
+        ];
+        if let Some(cursor) = self.cursor {
+            let (x, y) = self.window_to_cell(cursor);
+            let world_size = self.renderer.world_size() as i32;
+            let (x, y) = (x.rem_euclid(world_size), y.rem_euclid(world_size));
+            let report = self.renderer.with_cpu_world_buffer(|world| {
+                CellReport::of(
+                    world,
+                    world_size as u32,
+                    &self.options.kernel_arguments,
+                    x,
+                    y,
+                )
+            });
+            lines.push(format!(
+                "CELL {},{}  VALUE {}  SUM {}  NEXT {}",
+                x, y, report.value, report.neighborhood, report.next
+            ));
+        }
+        lines
+    }
+
+    fn toggle_hud(&mut self) {
+        self.options.hud = !self.options.hud;
+        if self.options.hud {
+            self.population_density = Stats::of(&self.renderer).population_density;
+        }
+    }
+
+    fn toggle_minimap(&mut self) {
+        self.options.minimap = !self.options.minimap;
+        println!(
+            "Minimap {}",
+            if self.options.minimap { "on" } else { "off" }
+        );
+    }
+
+    /// Centers the view on the part of the world clicked in the minimap. Returns false when the
+    /// click was not on the minimap.
+    fn click_minimap(&mut self, cursor: (f32, f32)) -> bool {
+        let (left, top, size) = match self.renderer.minimap_area(&self.options) {
+            Some(area) => area,
+            None => return false,
+        };
+        let (x, y) = self.window_to_image(cursor);
+        let (x, y) = (x - left as f32, y - top as f32);
+        if x < 0.0 || y < 0.0 || x >= size as f32 || y >= size as f32 {
+            return false;
+        }
+        let cells_per_pixel = self.renderer.world_size() as f32 / size as f32;
+        self.center_on(x * cells_per_pixel, y * cells_per_pixel);
+        true
     }
 
-    fn set_offset(&mut self, x: f32, y: f32) {
-        self.options.offset[0] = (x * WORLD_SIZE as f32) as i32;
-        self.options.offset[1] = (y * WORLD_SIZE as f32) as i32;
+    fn toggle_chart(&mut self) {
+        self.options.chart = !self.options.chart;
+    }
+
+    /// Returns false and prints why if the output directory could not be created.
+    fn create_output_dir(&self) -> bool {
+        match std::fs::create_dir_all(&self.output_dir) {
+            Ok(()) => true,
+            Err(err) => {
+                eprintln!("Could not create {}: {}", self.output_dir.display(), err);
+                false
+            }
+        }
     }
 
-    fn offset_zoom(&mut self, increment: bool) {
-        if increment {
-            self.options.zoom += 1;
+    /// Saves the history the chart shows as CSV.
+    fn export_history(&mut self) {
+        if !self.create_output_dir() {
+            return;
+        }
+        let filename = self.output_dir.join(format!(
+            "HISTORY PARAMS {} SEED {} GEN {}.csv",
+            rule::filename_part(&self.options.kernel_arguments),
+            self.options.seed,
+            self.generation
+        ));
+        match std::fs::write(&filename, self.history.to_csv()) {
+            Ok(()) => println!("{}", filename.display()),
+            Err(err) => eprintln!("Could not save {}: {}", filename.display(), err),
+        }
+    }
+
+    /// Matches the presented image to the window's new physical size.
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.window_size = (size.width, size.height);
+        self.dispatcher.invalidate_swapchain();
+        // Minimized windows have no size, the old image is kept until they are restored.
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
+        let image = self.dispatcher.resize_presented_image(self.window_size);
+        if let Some(comparison) = &mut self.comparison {
+            comparison.renderer.set_target_image(image.clone());
+        }
+        if let Some(gallery) = &mut self.gallery {
+            gallery.set_target_image(image.clone());
+        }
+        self.renderer.set_target_image(image);
+        self.update_panes();
+    }
+
+    /// Moves the view by a distance in image pixels.
+    fn pan_by(&mut self, dx: f32, dy: f32) {
+        self.options.offset[0] += dx / self.options.pixel_zoom();
+        self.options.offset[1] += dy / self.options.pixel_zoom();
+    }
+
+    /// Keeps the cell which was under the mouse under it.
+    fn continue_panning(&mut self, previous: Option<(f32, f32)>) {
+        if let (Some(previous), Some(cursor)) = (previous, self.cursor) {
+            let from = self.window_to_image(previous);
+            let to = self.window_to_image(cursor);
+            self.pan_by(from.0 - to.0, from.1 - to.1);
+        }
+    }
+
+    /// Pans by a fraction of the view, or a whole view with shift held.
+    fn pan_by_key(&mut self, x: f32, y: f32) {
+        let (width, height) = self.renderer.view_size(&self.options);
+        let fraction = if self.modifiers.shift() {
+            1.0
         } else {
-            self.options.zoom -= 1;
+            KEY_PAN_FRACTION
+        };
+        self.pan_by(x * width as f32 * fraction, y * height as f32 * fraction);
+    }
+
+    /// Pans so that the cell under the mouse is in the middle of the view.
+    fn center_on_cursor(&mut self) {
+        let cursor = match self.cursor {
+            Some(cursor) => cursor,
+            None => return,
+        };
+        let (x, y) = self.window_to_world(cursor);
+        self.center_on(x.floor() + 0.5, y.floor() + 0.5);
+    }
+
+    fn center_on(&mut self, x: f32, y: f32) {
+        let (width, height) = self.renderer.view_size(&self.options);
+        let zoom = self.options.pixel_zoom();
+        self.options.offset = [
+            x - width as f32 / 2.0 / zoom,
+            y - height as f32 / 2.0 / zoom,
+        ];
+    }
+
+    /// Switches between windowed and fullscreen. Exclusive fullscreen uses the monitor's largest
+    /// video mode, and falls back to borderless where video modes cannot be changed.
+    fn toggle_fullscreen(&mut self, exclusive: bool) {
+        let window = self.dispatcher.window();
+        if window.fullscreen().is_some() {
+            window.set_fullscreen(None);
+            println!("Windowed");
+            return;
         }
-        if self.options.zoom > 4 {
-            self.options.zoom = 4;
-        } else if self.options.zoom < 1 {
-            self.options.zoom = 1;
+        let video_mode = if exclusive {
+            window.current_monitor().and_then(|monitor| {
+                monitor.video_modes().max_by_key(|mode| {
+                    let size = mode.size();
+                    (size.width * size.height, mode.refresh_rate())
+                })
+            })
+        } else {
+            None
+        };
+        match video_mode {
+            Some(mode) => {
+                println!(
+                    "Exclusive fullscreen, {}x{} at {} Hz",
+                    mode.size().width,
+                    mode.size().height,
+                    mode.refresh_rate()
+                );
+                window.set_fullscreen(Some(Fullscreen::Exclusive(mode)));
+            }
+            None => {
+                if exclusive {
+                    println!("Exclusive fullscreen is not supported here, using borderless.");
+                } else {
+                    println!("Borderless fullscreen");
+                }
+                window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+            }
         }
-        println!("{}x zoom", self.options.zoom);
+    }
+
+    /// Goes back to showing the world from its top left corner at 1x zoom.
+    fn reset_view(&mut self) {
+        self.options.offset = [0.0, 0.0];
+        self.options.zoom = 1.0;
+        println!("View reset");
+    }
+
+    /// Multiplies the zoom by `factor`, keeping the cell under the mouse where it is. When the
+    /// mouse is outside the window the middle of the view stays put instead.
+    fn zoom_by(&mut self, factor: f32) {
+        let (view_width, view_height) = self.renderer.view_size(&self.options);
+        let anchor = match self.cursor {
+            Some(cursor) => self.window_to_view(cursor),
+            None => (view_width as f32 / 2.0, view_height as f32 / 2.0),
+        };
+        let old_zoom = self.options.pixel_zoom();
+        self.options.zoom = (self.options.zoom * factor).max(MIN_ZOOM).min(MAX_ZOOM);
+        let new_zoom = self.options.pixel_zoom();
+        self.options.offset[0] += anchor.0 / old_zoom - anchor.0 / new_zoom;
+        self.options.offset[1] += anchor.1 / old_zoom - anchor.1 / new_zoom;
+        println!("{:.3}x zoom", self.options.zoom);
+    }
+
+    fn on_mouse_wheel(&mut self, delta: MouseScrollDelta) {
+        let lines = match delta {
+            MouseScrollDelta::LineDelta(_, y) => y,
+            MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_LINE,
+        };
+        self.zoom_by(WHEEL_ZOOM_STEP.powf(lines));
+    }
+
+    fn next_view_mode(&mut self) {
+        self.options.view_mode = self.options.view_mode.next();
+        println!("{} view", self.options.view_mode.name());
+    }
+
+    fn next_destrobe(&mut self) {
+        self.options.destrobe = self.options.destrobe.next();
+        println!("De-strobing {}", self.options.destrobe.name());
+    }
+
+    fn next_downsampling(&mut self) {
+        self.options.downsampling = self.options.downsampling.next();
+        println!("{} downsampling", self.options.downsampling.name());
     }
 
     fn offset_rate(&mut self, increase: bool) {
@@ -194,6 +904,774 @@ impl AppData {
         self.skip_frames(1);
     }
 
+    fn new_seed(&mut self) {
+        self.options.seed = rand::random();
+        println!("Seed {}", self.options.seed);
+        self.reset_world();
+    }
+
+    /// Returns false and prints why if the image could not be loaded.
+    fn load_image(&mut self, filename: &Path) -> bool {
+        let max_value = rule::largest_value(&self.options.kernel_arguments);
+        let world_size = self.renderer.world_size();
+        let colors = self.palette_colors();
+        match import::load_world_image(filename, world_size, self.image_mapping, max_value, &colors)
+        {
+            Ok(world) => {
+                self.renderer.upload_world(&world[..]);
+                println!("Loaded {}", filename.display());
+                true
+            }
+            Err(message) => {
+                eprintln!("{}", message);
+                false
+            }
+        }
+    }
+
+    /// Converts a position in the window to world coordinates using the same mapping as
+    /// finalize.comp. The result is not wrapped around the edges of the world.
+    fn window_to_world(&self, position: (f32, f32)) -> (f32, f32) {
+        let (view_x, view_y) = self.window_to_view(position);
+        let zoom = self.options.pixel_zoom();
+        (
+            view_x / zoom + self.options.offset[0],
+            view_y / zoom + self.options.offset[1],
+        )
+    }
+
+    /// Converts a position in the window to a pixel of the pane it is in. Every pane shows the
+    /// same part of its world, so the same cell is under the mouse in all of them.
+    fn window_to_view(&self, position: (f32, f32)) -> (f32, f32) {
+        let (x, y) = self.window_to_image(position);
+        let mut panes: Vec<Pane> = self.options.pane.into_iter().collect();
+        if let Some(comparison) = &self.comparison {
+            panes.push(comparison.pane);
+            panes.extend(comparison.difference_pane);
+        }
+        match panes.iter().find(|pane| pane.contains(x, y)) {
+            Some(pane) => (x - pane.left as f32, y - pane.top as f32),
+            None => (x, y),
+        }
+    }
+
+    /// Converts a position in the window to a pixel of the presented image, which is stretched
+    /// over the whole window.
+    fn window_to_image(&self, position: (f32, f32)) -> (f32, f32) {
+        let (target_width, target_height) = self.renderer.target_size();
+        (
+            position.0 / self.window_size.0 as f32 * target_width as f32,
+            position.1 / self.window_size.1 as f32 * target_height as f32,
+        )
+    }
+
+    fn on_mouse_button(&mut self, button: MouseButton, state: ElementState) {
+        if state == ElementState::Released {
+            self.brush.end_stroke();
+            self.selection_anchor = None;
+            self.panning = false;
+            return;
+        }
+        let cursor = match self.cursor {
+            Some(cursor) => cursor,
+            None => return,
+        };
+        // The middle button pans whichever tool is in use.
+        if button == MouseButton::Middle {
+            self.panning = true;
+            return;
+        }
+        // Nothing else is on screen while the gallery is open.
+        if self.gallery.is_some() {
+            if button == MouseButton::Left {
+                self.pick_from_gallery(cursor);
+            }
+            return;
+        }
+        if button == MouseButton::Left && self.click_minimap(cursor) {
+            return;
+        }
+        // The cells under the space-time pane cannot be seen, so only navigating works there.
+        if self.tool != Tool::Navigate
+            && self.options.slice.is_some()
+            && self.window_to_view(cursor).1 >= self.renderer.slice_pane_top(&self.options) as f32
+        {
+            return;
+        }
+        match self.tool {
+            Tool::Navigate => {
+                self.panning = button == MouseButton::Left;
+                return;
+            }
+            Tool::Paint => (),
+            Tool::Stamp => {
+                if button == MouseButton::Left {
+                    self.place_stamp(cursor);
+                }
+                return;
+            }
+            Tool::Slice => {
+                match button {
+                    MouseButton::Left => self.pick_slice(SliceAxis::Row, cursor),
+                    MouseButton::Right => self.pick_slice(SliceAxis::Column, cursor),
+                    _ => (),
+                }
+                return;
+            }
+            Tool::Select => {
+                if button == MouseButton::Left {
+                    let cell = self.window_to_cell(cursor);
+                    self.selection_anchor = Some(cell);
+                    self.options.selection = Some(Selection::between(cell, cell));
+                } else if button == MouseButton::Right {
+                    self.options.selection = None;
+                }
+                return;
+            }
+        }
+        let value = match button {
+            MouseButton::Left => self.brush.value,
+            MouseButton::Right => 0,
+            _ => return,
+        };
+        let at = self.window_to_world(cursor);
+        let world_size = self.renderer.world_size();
+        let brush = &mut self.brush;
+        self.renderer
+            .edit_world(|world| brush.begin_stroke(world, world_size, value, at));
+    }
+
+    fn continue_painting(&mut self) {
+        let cursor = match self.cursor {
+            Some(cursor) if self.brush.is_painting() => cursor,
+            _ => return,
+        };
+        let to = self.window_to_world(cursor);
+        let world_size = self.renderer.world_size();
+        let brush = &mut self.brush;
+        self.renderer
+            .edit_world(|world| brush.continue_stroke(world, world_size, to));
+    }
+
+    /// Switches to `tool`, or back to navigating if it is already selected.
+    fn toggle_tool(&mut self, tool: Tool) {
+        self.tool = if self.tool == tool {
+            Tool::Navigate
+        } else {
+            tool
+        };
+        self.brush.end_stroke();
+        self.selection_anchor = None;
+        match self.tool {
+            Tool::Navigate => println!("Navigate mode"),
+            Tool::Paint => println!(
+                "Paint mode, brush value {} size {}",
+                self.brush.value, self.brush.size
+            ),
+            Tool::Select => println!("Select mode"),
+            Tool::Stamp => self.browse_stamps(),
+            Tool::Slice => {
+                println!("Slice mode, left click shows a row over time and right click a column")
+            }
+        }
+    }
+
+    /// Shows the row or column under the mouse in the space-time pane.
+    fn pick_slice(&mut self, axis: SliceAxis, cursor: (f32, f32)) {
+        let (x, y) = self.window_to_cell(cursor);
+        let world_size = self.renderer.world_size() as i32;
+        let index = match axis {
+            SliceAxis::Row => y,
+            SliceAxis::Column => x,
+        }
+        .rem_euclid(world_size) as u32;
+        self.options.slice = Some(Slice { axis, index });
+        println!("Space-time slice of {} {}", axis.name(), index);
+    }
+
+    fn close_slice(&mut self) {
+        self.options.slice = None;
+        println!("Space-time pane closed");
+    }
+
+    /// Reloads the stamps for the current rule and lists them.
+    fn browse_stamps(&mut self) {
+        self.stamps = stamps::list(
+            &self.stamp_dir,
+            &self.options.kernel_arguments,
+            self.renderer.world_size(),
+        );
+        self.stamps_rule = self.options.kernel_arguments;
+        if self.stamps.is_empty() {
+            println!(
+                "Stamp mode, no stamps saved for {}",
+                rule::notation(&self.options.kernel_arguments)
+            );
+            return;
+        }
+        self.stamp_index = self.stamp_index.min(self.stamps.len() - 1);
+        println!(
+            "Stamp mode, stamps for {}:",
+            rule::notation(&self.options.kernel_arguments)
+        );
+        for (index, stamp) in self.stamps.iter().enumerate() {
+            let marker = if index == self.stamp_index { '>' } else { ' ' };
+            println!(
+                "{} {} ({}x{})",
+                marker, stamp.name, stamp.pattern.width, stamp.pattern.height
+            );
+        }
+    }
+
+    /// Stamps from another rule are never offered, so the list is reloaded when the rule changes.
+    fn refresh_stamps(&mut self) {
+        if self.stamps_rule != self.options.kernel_arguments {
+            self.browse_stamps();
+        }
+    }
+
+    fn next_stamp(&mut self, forward: bool) {
+        self.refresh_stamps();
+        if self.stamps.is_empty() {
+            println!("No stamps to choose from.");
+            return;
+        }
+        let count = self.stamps.len();
+        self.stamp_index = if forward {
+            (self.stamp_index + 1) % count
+        } else {
+            (self.stamp_index + count - 1) % count
+        };
+        println!("Stamp {}", self.stamps[self.stamp_index].name);
+    }
+
+    /// Places the chosen stamp centered on the cell under the mouse.
+    fn place_stamp(&mut self, cursor: (f32, f32)) {
+        self.refresh_stamps();
+        let pattern = match self.stamps.get(self.stamp_index) {
+            Some(stamp) => &stamp.pattern,
+            None => return,
+        };
+        let (x, y) = self.window_to_cell(cursor);
+        let left = x - pattern.width as i32 / 2;
+        let top = y - pattern.height as i32 / 2;
+        let world_size = self.renderer.world_size();
+        self.renderer
+            .edit_world(|world| pattern.paste_into_world(world, world_size, left, top));
+    }
+
+    fn begin_naming_stamp(&mut self) {
+        if self.options.selection.is_none() {
+            println!("Select something to save as a stamp first.");
+            return;
+        }
+        self.stamp_name = Some(String::new());
+        println!("Type a name for the stamp and press Return, or Escape to cancel.");
+    }
+
+    fn type_character(&mut self, c: char) {
+        if let Some(name) = &mut self.stamp_name {
+            if stamps::is_name_character(c) {
+                name.push(c);
+                println!("Stamp name: {}", name);
+            }
+        }
+    }
+
+    /// Handles the keys which edit a stamp name while one is being typed.
+    fn on_stamp_name_key(&mut self, code: VirtualKeyCode) {
+        match code {
+            VirtualKeyCode::Return => {
+                let name = self.stamp_name.take().unwrap();
+                let name = name.trim();
+                if name.is_empty() {
+                    println!("Stamps need a name.");
+                } else {
+                    self.save_stamp(name);
+                }
+            }
+            VirtualKeyCode::Escape => {
+                self.stamp_name = None;
+                println!("Cancelled");
+            }
+            VirtualKeyCode::Back => {
+                let name = self.stamp_name.as_mut().unwrap();
+                name.pop();
+                println!("Stamp name: {}", name);
+            }
+            _ => (),
+        }
+    }
+
+    fn save_stamp(&mut self, name: &str) {
+        let pattern = match self.copy_selection() {
+            Some(pattern) => pattern,
+            None => return,
+        };
+        match stamps::save(
+            &self.stamp_dir,
+            &self.options.kernel_arguments,
+            name,
+            &pattern,
+        ) {
+            Ok(filename) => println!("Saved {}", filename.display()),
+            Err(err) => eprintln!("Could not save stamp {}: {}", name, err),
+        }
+        if self.tool == Tool::Stamp {
+            self.browse_stamps();
+        }
+    }
+
+    /// The cell under a position in the window, not wrapped around the edges of the world.
+    fn window_to_cell(&self, position: (f32, f32)) -> (i32, i32) {
+        let (x, y) = self.window_to_world(position);
+        (x.floor() as i32, y.floor() as i32)
+    }
+
+    fn continue_selecting(&mut self) {
+        if let (Some(anchor), Some(cursor)) = (self.selection_anchor, self.cursor) {
+            let cell = self.window_to_cell(cursor);
+            self.options.selection = Some(Selection::between(anchor, cell));
+        }
+    }
+
+    fn copy_selection(&mut self) -> Option<Pattern> {
+        let selection = match self.options.selection {
+            Some(selection) => selection,
+            None => {
+                println!("Nothing is selected.");
+                return None;
+            }
+        };
+        let world_size = self.renderer.world_size();
+        let pattern = self.renderer.with_cpu_world_buffer(|world| {
+            Pattern::copy_from_world(
+                world,
+                world_size,
+                selection.left,
+                selection.top,
+                selection.width,
+                selection.height,
+            )
+        });
+        self.clipboard = Some(pattern.clone());
+        println!("Copied {}x{}", selection.width, selection.height);
+        Some(pattern)
+    }
+
+    fn cut_selection(&mut self) {
+        if self.copy_selection().is_some() {
+            self.clear_selection();
+        }
+    }
+
+    /// Pastes with the top left corner under the mouse, or over the selection when the mouse is
+    /// outside the window. The pasted cells become the new selection.
+    fn paste(&mut self) {
+        let pattern = match &self.clipboard {
+            Some(pattern) => pattern.clone(),
+            None => {
+                println!("Nothing has been copied yet.");
+                return;
+            }
+        };
+        let (left, top) = match (self.cursor, self.options.selection) {
+            (Some(cursor), _) => self.window_to_cell(cursor),
+            (None, Some(selection)) => (selection.left, selection.top),
+            (None, None) => {
+                let (left, top, _, _) = self.visible_region();
+                (left, top)
+            }
+        };
+        let world_size = self.renderer.world_size();
+        self.renderer
+            .edit_world(|world| pattern.paste_into_world(world, world_size, left, top));
+        self.options.selection = Some(Selection {
+            left,
+            top,
+            width: pattern.width,
+            height: pattern.height,
+        });
+    }
+
+    /// Replaces every selected cell with whatever `fill` returns for it.
+    fn fill_selection(&mut self, mut fill: impl FnMut() -> u16) {
+        let selection = match self.options.selection {
+            Some(selection) => selection,
+            None => {
+                println!("Nothing is selected.");
+                return;
+            }
+        };
+        let mut pattern = Pattern::new(selection.width, selection.height);
+        for cell in &mut pattern.cells {
+            *cell = fill();
+        }
+        let world_size = self.renderer.world_size();
+        self.renderer.edit_world(|world| {
+            pattern.paste_into_world(world, world_size, selection.left, selection.top)
+        });
+    }
+
+    fn clear_selection(&mut self) {
+        self.fill_selection(|| 0);
+    }
+
+    /// Fills the selection with the same kind of noise as the noise soup.
+    fn randomize_selection(&mut self) {
+        let density = self.options.soup.density;
+        let max_value = self
+            .options
+            .soup
+            .values
+            .max_value(self.options.kernel_arguments[0]) as u16;
+        let mut rng = rand::thread_rng();
+        self.fill_selection(|| {
+            if rng.gen::<f32>() < density {
+                rng.gen_range(1..=max_value)
+            } else {
+                0
+            }
+        });
+    }
+
+    /// Replaces the selection with a transformed copy of itself, keeping it centered where it
+    /// was.
+    fn transform_selection(&mut self, transform: impl FnOnce(&Pattern) -> Pattern) {
+        let selection = match self.options.selection {
+            Some(selection) => selection,
+            None => {
+                println!("Nothing is selected.");
+                return;
+            }
+        };
+        let world_size = self.renderer.world_size();
+        let pattern = self.renderer.with_cpu_world_buffer(|world| {
+            Pattern::copy_from_world(
+                world,
+                world_size,
+                selection.left,
+                selection.top,
+                selection.width,
+                selection.height,
+            )
+        });
+        let transformed = transform(&pattern);
+        let target = selection.resized_around_center(transformed.width, transformed.height);
+        self.renderer.edit_world(|world| {
+            Pattern::new(selection.width, selection.height).paste_into_world(
+                world,
+                world_size,
+                selection.left,
+                selection.top,
+            );
+            transformed.paste_into_world(world, world_size, target.left, target.top);
+        });
+        self.options.selection = Some(target);
+    }
+
+    fn on_selection_key(&mut self, code: VirtualKeyCode) {
+        match code {
+            VirtualKeyCode::A => {
+                let (left, top, width, height) = self.visible_region();
+                self.options.selection = Some(Selection {
+                    left,
+                    top,
+                    width,
+                    height,
+                });
+            }
+            VirtualKeyCode::D => self.options.selection = None,
+            VirtualKeyCode::C => {
+                self.copy_selection();
+            }
+            VirtualKeyCode::X => self.cut_selection(),
+            VirtualKeyCode::V => self.paste(),
+            VirtualKeyCode::N => self.randomize_selection(),
+            VirtualKeyCode::S => self.begin_naming_stamp(),
+            VirtualKeyCode::R if self.modifiers.shift() => {
+                self.transform_selection(Pattern::rotated_counterclockwise)
+            }
+            VirtualKeyCode::R => self.transform_selection(Pattern::rotated_clockwise),
+            VirtualKeyCode::F if self.modifiers.shift() => {
+                self.transform_selection(Pattern::flipped_vertically)
+            }
+            VirtualKeyCode::F => self.transform_selection(Pattern::flipped_horizontally),
+            _ => (),
+        }
+    }
+
+    fn next_brush_value(&mut self) {
+        let largest = rule::largest_value(&self.options.kernel_arguments);
+        self.brush.next_value(largest);
+        println!("Brush value {}", self.brush.value);
+    }
+
+    fn next_brush_size(&mut self) {
+        self.brush.next_size();
+        println!("Brush size {}", self.brush.size);
+    }
+
+    /// The rectangle of the world which is on screen, as left, top, width and height.
+    fn visible_region(&self) -> (i32, i32, usize, usize) {
+        let (width, height) = self.renderer.view_size(&self.options);
+        let zoom = self.options.pixel_zoom();
+        let world_size = self.renderer.world_size() as f32;
+        (
+            self.options.offset[0].floor() as i32,
+            self.options.offset[1].floor() as i32,
+            (width as f32 / zoom).ceil().min(world_size) as usize,
+            (height as f32 / zoom).ceil().min(world_size) as usize,
+        )
+    }
+
+    /// Exports the selection, or everything on screen when nothing is selected.
+    fn export_pattern(&mut self) {
+        let (left, top, width, height) = match self.options.selection {
+            Some(selection) => (
+                selection.left,
+                selection.top,
+                selection.width,
+                selection.height,
+            ),
+            None => self.visible_region(),
+        };
+        let world_size = self.renderer.world_size();
+        let pattern = self.renderer.with_cpu_world_buffer(|world| {
+            Pattern::copy_from_world(world, world_size, left, top, width, height)
+        });
+        let comments = [format!(
+            "Generation {}, seed {}",
+            self.generation, self.options.seed
+        )];
+        let text = match rle::write(
+            &pattern,
+            &rule::notation(&self.options.kernel_arguments),
+            &comments,
+        ) {
+            Ok(text) => text,
+            Err(message) => {
+                eprintln!("Could not export the pattern: {}", message);
+                return;
+            }
+        };
+        if !self.create_output_dir() {
+            return;
+        }
+        let filename = self.output_dir.join(format!(
+            "PATTERN PARAMS {} SEED {} GEN {}.rle",
+            rule::filename_part(&self.options.kernel_arguments),
+            self.options.seed,
+            self.generation
+        ));
+        match std::fs::write(&filename, text) {
+            Ok(()) => println!("{}", filename.display()),
+            Err(err) => eprintln!("Could not save {}: {}", filename.display(), err),
+        }
+    }
+
+    /// Pastes an RLE pattern into the middle of the view, switching to its rule if it has one.
+    /// Returns false and prints why if the pattern could not be loaded.
+    fn load_pattern(&mut self, filename: &Path) -> bool {
+        let world_size = self.renderer.world_size();
+        let parsed = std::fs::read_to_string(filename)
+            .map_err(|err| err.to_string())
+            .and_then(|text| rle::parse(&text, world_size as usize));
+        let parsed = match parsed {
+            Ok(parsed) => parsed,
+            Err(message) => {
+                eprintln!("Could not load {}: {}", filename.display(), message);
+                return false;
+            }
+        };
+        if let Some(rule_text) = &parsed.rule {
+            match rule::parse(rule_text) {
+                Ok(arguments) => self.options.kernel_arguments = arguments,
+                Err(_) => println!(
+                    "\"{}\" is not a Square Sum Map rule, keeping the current rule.",
+                    rule_text
+                ),
+            }
+        }
+        let pattern = parsed.pattern;
+        let (left, top, width, height) = self.visible_region();
+        let left = left + (width as i32 - pattern.width as i32) / 2;
+        let top = top + (height as i32 - pattern.height as i32) / 2;
+        self.renderer
+            .edit_world(|world| pattern.paste_into_world(world, world_size, left, top));
+        println!("Loaded {}", filename.display());
+        true
+    }
+
+    fn take_snapshot(&self) -> WorldSnapshot {
+        WorldSnapshot {
+            kernel_arguments: self.options.kernel_arguments,
+            generation: self.generation,
+            seed: self.options.seed,
+            world_size: self.renderer.world_size(),
+            cells: self.renderer.with_cpu_world_buffer(Vec::from),
+        }
+    }
+
+    fn save_snapshot(&mut self, filename: &Path) {
+        match self.take_snapshot().save(filename) {
+            Ok(()) => {
+                println!("Saved {}", filename.display());
+                self.snapshot_file = Some(filename.to_owned());
+            }
+            Err(err) => eprintln!("Could not save {}: {}", filename.display(), err),
+        }
+    }
+
+    /// Saves the cells in the main view at its zoom and offset, or the whole world at one pixel per
+    /// cell, in plain palette colors. See `export::cell_view_pixels` for what is left out.
+    fn save_screenshot(&self, filename: &Path, whole_world: bool) {
+        let world_size = self.renderer.world_size();
+        let colors = self.palette_colors();
+        let mut text = self.capture_metadata();
+        text.push(("generation", self.generation.to_string()));
+        let result = self.renderer.with_cpu_world_buffer(|world| {
+            if whole_world {
+                export::save_world_png(world, world_size, &colors, &text, filename)
+            } else {
+                let (width, height) = self.renderer.view_size(&self.options);
+                let pixels = export::cell_view_pixels(
+                    world,
+                    world_size,
+                    &colors,
+                    self.options.offset,
+                    self.options.pixel_zoom(),
+                    (width, height),
+                );
+                export::save_png(&pixels, width, height, &text, filename)
+            }
+        });
+        match result {
+            Ok(()) => println!("Saved {}", filename.display()),
+            Err(err) => eprintln!("Could not save {}: {}", filename.display(), err),
+        }
+    }
+
+    fn screenshot(&mut self, whole_world: bool) {
+        let filename = match &self.screenshot_target {
+            Some(filename) => filename.clone(),
+            None => {
+                if !self.create_output_dir() {
+                    return;
+                }
+                self.output_dir.join(format!(
+                    "{} PARAMS {} SEED {} GEN {}.png",
+                    if whole_world { "WORLD" } else { "CELLS" },
+                    rule::filename_part(&self.options.kernel_arguments),
+                    self.options.seed,
+                    self.generation
+                ))
+            }
+        };
+        self.save_screenshot(&filename, whole_world);
+    }
+
+    fn quick_save(&mut self) {
+        let filename = match &self.save_target {
+            Some(filename) => filename.clone(),
+            None => {
+                if !self.create_output_dir() {
+                    return;
+                }
+                self.take_snapshot().default_filename(&self.output_dir)
+            }
+        };
+        self.save_snapshot(&filename);
+    }
+
+    fn quick_load(&mut self) {
+        match self.snapshot_file.clone() {
+            Some(filename) => {
+                self.load_snapshot(&filename);
+            }
+            None => println!("No snapshot has been saved or loaded yet."),
+        }
+    }
+
+    /// Returns false and prints why if the snapshot could not be loaded.
+    fn load_snapshot(&mut self, filename: &Path) -> bool {
+        let snapshot = match WorldSnapshot::load(filename) {
+            Ok(snapshot) => snapshot,
+            Err(message) => {
+                eprintln!("{}", message);
+                return false;
+            }
+        };
+        if snapshot.world_size != self.renderer.world_size() {
+            eprintln!(
+                "{} is {} cells wide but the world is {}, use --load-world to change the size.",
+                filename.display(),
+                snapshot.world_size,
+                self.renderer.world_size()
+            );
+            return false;
+        }
+        self.restore_snapshot(snapshot);
+        self.snapshot_file = Some(filename.to_owned());
+        println!("Loaded {}", filename.display());
+        true
+    }
+
+    fn restore_snapshot(&mut self, snapshot: WorldSnapshot) {
+        self.options.kernel_arguments = snapshot.kernel_arguments;
+        self.options.seed = snapshot.seed;
+        self.restored_generation = Some(snapshot.generation);
+        self.renderer.upload_world(&snapshot.cells[..]);
+    }
+
+    /// The colors of the current palette for the current rule.
+    fn palette_colors(&self) -> Vec<Color> {
+        self.options
+            .palette
+            .colors(rule::largest_value(&self.options.kernel_arguments))
+    }
+
+    /// Cycles through the built-in palettes and the one from the command line, if it was loaded
+    /// from a file.
+    fn next_palette(&mut self) {
+        let mut palettes = Palette::BUILT_IN.to_vec();
+        if let Palette::File { .. } = &self.command_line_palette {
+            palettes.push(self.command_line_palette.clone());
+        }
+        let index = palettes
+            .iter()
+            .position(|palette| *palette == self.options.palette)
+            .map_or(0, |index| (index + 1) % palettes.len());
+        self.options.palette = palettes[index].clone();
+        println!("{} palette", self.options.palette.name());
+    }
+
+    fn next_generator(&mut self) {
+        self.options.soup.generator = self.options.soup.generator.next();
+        println!("{} soup", self.options.soup.generator.name());
+        self.reset_world();
+    }
+
+    /// Everything needed to reproduce the current world from scratch.
+    fn capture_metadata(&self) -> Vec<(&'static str, String)> {
+        let soup = &self.options.soup;
+        vec![
+            ("rule", rule::notation(&self.options.kernel_arguments)),
+            ("seed", self.options.seed.to_string()),
+            ("generator", soup.generator.name().to_string()),
+            ("density", soup.density.to_string()),
+            (
+                "max_value",
+                soup.values
+                    .max_value(self.options.kernel_arguments[0])
+                    .to_string(),
+            ),
+            ("feature_size", soup.feature_size.to_string()),
+            ("octaves", soup.octaves.to_string()),
+            ("world_size", self.renderer.world_size().to_string()),
+        ]
+    }
+
     fn offset_arguments(&mut self, increase: bool) {
         let divisor = self.options.kernel_arguments[0];
         if increase {
@@ -221,7 +1699,7 @@ impl AppData {
             display: false,
             ..self.options.clone()
         };
-        self.renderer.render(&mut self.dispatcher, &test_options);
+        self.render_with(&test_options);
         let mut judge = Judge::new(Stats::of(&self.renderer));
         let test_options = Options {
             reset: false,
@@ -229,7 +1707,7 @@ impl AppData {
             ..test_options
         };
         for _ in 0..4 {
-            self.renderer.render(&mut self.dispatcher, &test_options);
+            self.render_with(&test_options);
             judge.push_snapshot(Stats::of(&self.renderer));
             let judgement = judge.judgement();
             if !judgement.is_unknown() {
@@ -250,44 +1728,165 @@ impl AppData {
         };
         let mut scorer = Scorer::new();
         for _ in 0..100 {
-            self.renderer.render(&mut self.dispatcher, &test_options);
+            self.render_with(&test_options);
             scorer.add_snapshot(&self.renderer);
         }
         let densities = scorer.find_pattern_densities();
         let score = scorer.compute_score(&densities[..]);
-        let params = &self.options.kernel_arguments[..];
-        let last_zero = params.len() - params.iter().rev().position(|a| *a != 0).unwrap();
-        let params = (&params[..last_zero])
-            .iter()
-            .map(|e| e.to_string())
-            .collect::<Vec<_>>()
-            .join("-");
-        let filename = format!("captures/9/SCORE {:08.2} PARAMS {}.gif", score, params);
-        println!("{}", filename);
-        scorer.create_gif(&densities[..], &filename[..]);
+        // The score is still worth printing when its GIF cannot be saved.
+        if !self.create_output_dir() {
+            return score;
+        }
+        let params = rule::filename_part(&self.options.kernel_arguments);
+        let filename = self.output_dir.join(format!(
+            "SCORE {:08.2} PARAMS {} SEED {}.gif",
+            score, params, self.options.seed
+        ));
+        println!("{}", filename.display());
+        scorer.create_gif(&densities[..], &self.palette_colors(), &filename);
+        let mut metadata = self.capture_metadata();
+        metadata.push(("score", score.to_string()));
+        if let Err(err) = export::write_metadata(&filename, &metadata) {
+            eprintln!(
+                "Could not save the metadata of {}: {}",
+                filename.display(),
+                err
+            );
+        }
         score
     }
 
+    fn reset_headless(&mut self) {
+        let reset_options = Options {
+            reset: true,
+            rate: 0,
+            skip: 0,
+            display: false,
+            ..self.options.clone()
+        };
+        self.render_with(&reset_options);
+    }
+
+    /// Judges and scores every rule from the current one up to and including `until`, or until
+    /// the odometer runs out of rules with the current divisor.
+    fn search(&mut self, until: Option<Box<KernelArguments>>) {
+        loop {
+            let arguments = self.options.kernel_arguments;
+            if arguments[1] >= arguments[0] {
+                break;
+            }
+            if let Some(until) = &until {
+                if arguments > **until {
+                    break;
+                }
+            }
+            println!("{}", rule::notation(&arguments));
+            if self.compute_judgement().is_interesting() {
+                println!("{:?}", self.compute_score());
+            }
+            self.offset_arguments(true);
+        }
+    }
+
+    /// Returns false and prints why if the image could not be saved.
+    fn render_to_file(&mut self, generations: u32, filename: &Path) -> bool {
+        self.reset_headless();
+        let render_options = Options {
+            reset: false,
+            rate: 0,
+            skip: generations,
+            display: false,
+            ..self.options.clone()
+        };
+        self.render_with(&render_options);
+        let world_size = self.renderer.world_size();
+        let colors = self.palette_colors();
+        let mut metadata = self.capture_metadata();
+        metadata.push(("generation", self.generation.to_string()));
+        let result = self
+            .renderer
+            .with_cpu_world_buffer(|world| {
+                export::save_world_png(world, world_size, &colors, &metadata, filename)
+            })
+            .and_then(|()| export::write_metadata(filename, &metadata));
+        match result {
+            Ok(()) => {
+                println!("{}", filename.display());
+                true
+            }
+            Err(err) => {
+                eprintln!("Could not save {}: {}", filename.display(), err);
+                false
+            }
+        }
+    }
+
     fn skip_uninteresting(&mut self) {
         self.offset_arguments(true);
         while !self.compute_judgement().is_interesting() {
             self.offset_arguments(true);
         }
-        let divisor = self.options.kernel_arguments[0];
-        for argument in &self.options.kernel_arguments[0..1 + divisor as usize] {
-            print!("{} ", argument);
-        }
-        println!("");
+        println!("{}", rule::notation(&self.options.kernel_arguments));
         println!("{:?}", self.compute_score());
     }
 
     fn on_key(&mut self, code: VirtualKeyCode) {
+        if self.stamp_name.is_some() {
+            self.on_stamp_name_key(code);
+            return;
+        }
+        if self.modifiers.ctrl() {
+            self.on_selection_key(code);
+            return;
+        }
         match code {
-            VirtualKeyCode::Equals => self.offset_zoom(true),
-            VirtualKeyCode::Minus => self.offset_zoom(false),
+            VirtualKeyCode::Equals => self.zoom_by(KEY_ZOOM_STEP),
+            VirtualKeyCode::Minus => self.zoom_by(1.0 / KEY_ZOOM_STEP),
+            VirtualKeyCode::D => self.next_downsampling(),
+            VirtualKeyCode::Tab => self.next_view_mode(),
+            VirtualKeyCode::H => self.toggle_hud(),
+            VirtualKeyCode::W => self.toggle_minimap(),
+            VirtualKeyCode::Y if self.modifiers.shift() => self.toggle_differences(),
+            VirtualKeyCode::Y => self.toggle_comparison(),
+            VirtualKeyCode::I => self.toggle_gallery(),
+            VirtualKeyCode::F12 if self.modifiers.shift() => self.screenshot(true),
+            VirtualKeyCode::F12 => self.screenshot(false),
+            VirtualKeyCode::K if self.modifiers.shift() => self.export_history(),
+            VirtualKeyCode::K => self.toggle_chart(),
+            VirtualKeyCode::O => self.next_destrobe(),
+            VirtualKeyCode::Left => self.pan_by_key(-1.0, 0.0),
+            VirtualKeyCode::Right => self.pan_by_key(1.0, 0.0),
+            VirtualKeyCode::Up => self.pan_by_key(0.0, -1.0),
+            VirtualKeyCode::Down => self.pan_by_key(0.0, 1.0),
+            VirtualKeyCode::Z => self.center_on_cursor(),
+            VirtualKeyCode::Home => self.reset_view(),
+            VirtualKeyCode::F11 => self.toggle_fullscreen(self.modifiers.shift()),
             VirtualKeyCode::Comma => self.offset_rate(false),
             VirtualKeyCode::Period => self.offset_rate(true),
             VirtualKeyCode::R => self.reset_world(),
+            VirtualKeyCode::N => self.new_seed(),
+            VirtualKeyCode::G => self.next_generator(),
+            VirtualKeyCode::C => self.next_palette(),
+            VirtualKeyCode::S => self.quick_save(),
+            VirtualKeyCode::L => self.quick_load(),
+            VirtualKeyCode::E => self.export_pattern(),
+            VirtualKeyCode::P => self.toggle_tool(Tool::Paint),
+            VirtualKeyCode::M => self.toggle_tool(Tool::Select),
+            VirtualKeyCode::T => self.toggle_tool(Tool::Stamp),
+            VirtualKeyCode::Q if self.modifiers.shift() => self.close_slice(),
+            VirtualKeyCode::Q => self.toggle_tool(Tool::Slice),
+            VirtualKeyCode::LBracket => self.next_stamp(false),
+            VirtualKeyCode::RBracket => self.next_stamp(true),
+            VirtualKeyCode::Delete => self.clear_selection(),
+            VirtualKeyCode::V => self.next_brush_value(),
+            VirtualKeyCode::B => self.next_brush_size(),
+            VirtualKeyCode::X => {
+                golly::save_rule_file(
+                    &self.options.kernel_arguments,
+                    &self.options.palette,
+                    &self.output_dir,
+                );
+            }
             VirtualKeyCode::F => {
                 self.skip_frames(1);
                 self.pause();
@@ -304,6 +1903,17 @@ impl AppData {
     }
 
     fn after_frame(&mut self) {
+        if self.speed_meter.add_frame() && self.options.hud {
+            self.population_density = Stats::of(&self.renderer).population_density;
+        }
+        let renderer = &self.renderer;
+        let min_spacing = if self.options.chart {
+            1
+        } else {
+            HIDDEN_HISTORY_SPACING
+        };
+        self.history
+            .record(self.generation, min_spacing, || Stats::of(renderer));
         self.options.reset = false;
         self.options.skip = 0;
     }
@@ -0,0 +1,150 @@
+const SIZES: [u32; 5] = [1, 2, 4, 8, 16];
+
+/// A stroke which is being painted while a mouse button is held down.
+struct Stroke {
+    value: u16,
+    /// Where the previous dab went, in world coordinates which have not been wrapped.
+    last: (f32, f32),
+}
+
+pub struct Brush {
+    /// The value the left mouse button paints. The right mouse button always paints zero.
+    pub value: u16,
+    /// Width of the square the brush paints, in cells.
+    pub size: u32,
+    stroke: Option<Stroke>,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self {
+            value: 1,
+            size: 1,
+            stroke: None,
+        }
+    }
+}
+
+impl Brush {
+    /// Cycles through every nonzero value up to `largest`.
+    pub fn next_value(&mut self, largest: u16) {
+        self.value = if self.value >= largest {
+            1
+        } else {
+            self.value + 1
+        };
+    }
+
+    pub fn next_size(&mut self) {
+        let index = SIZES
+            .iter()
+            .position(|&size| size == self.size)
+            .unwrap_or(0);
+        self.size = SIZES[(index + 1) % SIZES.len()];
+    }
+
+    pub fn is_painting(&self) -> bool {
+        self.stroke.is_some()
+    }
+
+    pub fn begin_stroke(&mut self, world: &mut [u16], world_size: u32, value: u16, at: (f32, f32)) {
+        self.stroke = Some(Stroke { value, last: at });
+        self.dab(world, world_size, value, at);
+    }
+
+    /// Paints a line from the end of the stroke so far, so that fast mouse movements do not leave
+    /// gaps.
+    pub fn continue_stroke(&mut self, world: &mut [u16], world_size: u32, to: (f32, f32)) {
+        let (value, from) = match &self.stroke {
+            Some(stroke) => (stroke.value, stroke.last),
+            None => return,
+        };
+        let steps = (to.0 - from.0)
+            .abs()
+            .max((to.1 - from.1).abs())
+            .ceil()
+            .max(1.0) as u32;
+        for step in 1..=steps {
+            let amount = step as f32 / steps as f32;
+            let at = (
+                from.0 + (to.0 - from.0) * amount,
+                from.1 + (to.1 - from.1) * amount,
+            );
+            self.dab(world, world_size, value, at);
+        }
+        self.stroke = Some(Stroke { value, last: to });
+    }
+
+    pub fn end_stroke(&mut self) {
+        self.stroke = None;
+    }
+
+    fn dab(&self, world: &mut [u16], world_size: u32, value: u16, at: (f32, f32)) {
+        let size = self.size as i32;
+        let left = at.0.floor() as i32 - size / 2;
+        let top = at.1.floor() as i32 - size / 2;
+        let world_size = world_size as i32;
+        for y in top..top + size {
+            for x in left..left + size {
+                let index = y.rem_euclid(world_size) * world_size + x.rem_euclid(world_size);
+                world[index as usize] = value;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_and_sizes_cycle() {
+        let mut brush = Brush::default();
+        brush.next_value(2);
+        assert_eq!(brush.value, 2);
+        brush.next_value(2);
+        assert_eq!(brush.value, 1);
+        brush.value = 7;
+        brush.next_value(3);
+        assert_eq!(brush.value, 1);
+
+        let sizes: Vec<_> = (0..SIZES.len())
+            .map(|_| {
+                brush.next_size();
+                brush.size
+            })
+            .collect();
+        assert_eq!(sizes, vec![2, 4, 8, 16, 1]);
+    }
+
+    #[test]
+    fn dabs_wrap_around_the_edges_of_the_world() {
+        let mut world = vec![0; 64];
+        let mut brush = Brush {
+            size: 2,
+            ..Brush::default()
+        };
+        brush.begin_stroke(&mut world, 8, 3, (0.5, 0.5));
+        brush.end_stroke();
+        let painted: Vec<_> = (0..64).filter(|&index| world[index] != 0).collect();
+        assert_eq!(painted, vec![0, 7, 56, 63]);
+        assert!(painted.iter().all(|&index| world[index] == 3));
+    }
+
+    #[test]
+    fn strokes_leave_no_gaps() {
+        let mut world = vec![0; 64];
+        let mut brush = Brush::default();
+        brush.begin_stroke(&mut world, 8, 2, (1.5, 1.5));
+        assert!(brush.is_painting());
+        brush.continue_stroke(&mut world, 8, (6.5, 1.5));
+        brush.end_stroke();
+        assert!(!brush.is_painting());
+        assert_eq!(&world[8..16], &[0, 2, 2, 2, 2, 2, 2, 0]);
+        assert_eq!(world.iter().filter(|&&cell| cell != 0).count(), 6);
+
+        // Moving without a stroke paints nothing.
+        brush.continue_stroke(&mut world, 8, (1.5, 5.5));
+        assert_eq!(world.iter().filter(|&&cell| cell != 0).count(), 6);
+    }
+}
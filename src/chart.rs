@@ -0,0 +1,95 @@
+//! A chart of the world's history drawn into the HUD. The population density is drawn in white
+//! over a line for every nonzero value in its palette color, all as fractions of the world.
+
+use crate::{
+    hud::{HudImage, HUD_PANEL, HUD_TEXT, HUD_VALUE, HUD_WIDTH, LINE_HEIGHT, MARGIN},
+    palette::PALETTE_SIZE,
+    stats::History,
+};
+
+/// Height of the chart in HUD pixels, including its label.
+pub const CHART_HEIGHT: usize = 120;
+
+/// Draws a line from `from` to `to`, one pixel per step along its longer axis.
+fn draw_line(image: &mut HudImage, from: (usize, usize), to: (usize, usize), pixel: u16) {
+    let (dx, dy) = (to.0 as f32 - from.0 as f32, to.1 as f32 - from.1 as f32);
+    let steps = dx.abs().max(dy.abs()).max(1.0) as usize;
+    for step in 0..=steps {
+        let amount = step as f32 / steps as f32;
+        let x = from.0 as f32 + dx * amount;
+        let y = from.1 as f32 + dy * amount;
+        image.set(x.round() as usize, y.round() as usize, pixel);
+    }
+}
+
+/// Draws the chart with its top at `top`.
+pub fn draw(image: &mut HudImage, top: usize, history: &History) {
+    let width = HUD_WIDTH as usize;
+    image.fill(0, top, width, CHART_HEIGHT, HUD_PANEL);
+    let samples = history.samples();
+    let last = match samples.last() {
+        Some(last) => last,
+        None => {
+            image.text(MARGIN, top + MARGIN, "NO GENERATIONS YET");
+            return;
+        }
+    };
+    let fraction = |count: u32, total: u32| count as f32 / total.max(1) as f32;
+    // Scaled to the largest fraction so that sparse worlds are not squashed against the bottom.
+    let largest = samples
+        .iter()
+        .flat_map(|sample| {
+            let stats = &sample.stats;
+            let counts = stats.value_counts.iter().skip(1);
+            counts
+                .map(move |&count| fraction(count, stats.total_cells))
+                .chain(std::iter::once(stats.population_density))
+        })
+        .fold(0.0f32, f32::max)
+        .max(1e-6);
+    image.text(
+        MARGIN,
+        top + MARGIN,
+        &format!(
+            "GEN {}  DENSITY {:.4}  TOP {:.4}",
+            last.generation, last.stats.population_density, largest
+        ),
+    );
+
+    let plot_top = top + MARGIN + LINE_HEIGHT;
+    let plot_height = CHART_HEIGHT - MARGIN * 2 - LINE_HEIGHT;
+    let plot_width = width - MARGIN * 2;
+    let point = |index: usize, fraction: f32| {
+        let x = MARGIN + index * (plot_width - 1) / (samples.len() - 1).max(1);
+        let height = (fraction / largest * (plot_height - 1) as f32).round() as usize;
+        (x, plot_top + plot_height - 1 - height.min(plot_height - 1))
+    };
+    let mut draw_series = |fraction_of: &dyn Fn(usize) -> f32, pixel: u16| {
+        for index in 0..samples.len() {
+            let to = point(index, fraction_of(index));
+            let from = if index == 0 {
+                to
+            } else {
+                point(index - 1, fraction_of(index - 1))
+            };
+            draw_line(image, from, to, pixel);
+        }
+    };
+    let values = samples
+        .iter()
+        .map(|sample| sample.stats.value_counts.len())
+        .max()
+        .unwrap_or(0)
+        .min(PALETTE_SIZE);
+    for value in 1..values {
+        draw_series(
+            &|index| {
+                let stats = &samples[index].stats;
+                let count = stats.value_counts.get(value).copied().unwrap_or(0);
+                fraction(count, stats.total_cells)
+            },
+            HUD_VALUE + value as u16,
+        );
+    }
+    draw_series(&|index| samples[index].stats.population_density, HUD_TEXT);
+}
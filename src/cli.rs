@@ -0,0 +1,435 @@
+use std::path::PathBuf;
+
+use vulkano::swapchain::PresentMode;
+
+use crate::{
+    import::ImageMapping,
+    options::{self, DEFAULT_WORLD_SIZE},
+    palette::Palette,
+    rule::{self, KernelArguments},
+    soup::{Soup, ValueRange},
+};
+
+const USAGE: &str = "\
+usage: matrix-3 <command> [flags]
+
+commands:
+    view <rule> [--compare <rule>] [--compare-seed <n>]
+                                            open the interactive viewer, beside a second world
+                                            with its own rule and seed when either is given
+    search --from <rule> [--until <rule>]   headlessly judge and score every rule in a range
+    score <rule>                            compute the score of a rule and save its GIF
+    judge <rule>                            print the automatic judgement of a rule
+    render <rule> --generations <n> --out <file.png>
+                                            save the whole world after n generations
+    golly <rule>                            save the rule as a Golly .rule file in the output
+                                            directory
+
+rules are written as \"4 % 0 0 0 1\" (quoted) or as \"4-0-0-0-1\".
+
+common flags:
+    --size <cells>      width and height of the world, a multiple of 8 (default 1024)
+    --seed <n>          which random soup the world starts from (default 0)
+    --generator <name>  what the world starts as: noise, uniform, cell, blob, mirror, rotational
+                        or stripes (default noise)
+    --density <d>       fraction of soup cells which start nonzero, except with the noise
+                        generator (default 0.5)
+    --values <n>        soup cells get values from 1 to n, or \"divisor\" for every value below
+                        the divisor (default 9)
+    --feature-size <n>  radius of blobs and width of stripes (default 64)
+    --octaves <n>       layers of noise in the noise mask, 1 to 4 (default 2)
+    --output-dir <dir>  where captures are saved (default captures/9)
+    --load-image <file> start from an image instead of a soup, images can also be dropped onto
+                        the viewer window
+    --load-world <file> start from a saved world snapshot (.m3w), which also sets the rule, seed
+                        and world size. Snapshots can also be dropped onto the viewer window
+    --load-pattern <f>  paste an RLE pattern into the middle of the world, which is empty unless
+                        something else is loaded, and switch to its rule if it has one. Patterns
+                        can also be dropped onto the viewer window
+    --save-world <file> where the viewer's save key writes snapshots to, headless commands save
+                        the final world there
+    --screenshot <file> where the viewer's screenshot keys write PNGs to, headless commands save
+                        the whole final world there at one pixel per cell
+    --stamp-dir <dir>   where stamps are kept, in a directory for each rule (default stamps)
+    --image-mapping <m> how image pixels become cells: palette picks the value with the closest
+                        on-screen color, grayscale spreads values from black to white
+                        (default palette)
+    --palette <p>       the colors cells are shown and saved in: classic, colorblind, grayscale or
+                        a palette file with one color per line, as hex or three numbers
+                        (default classic)
+    --present-mode <m>  fifo waits for vertical sync, mailbox replaces frames which have not been
+                        shown yet and immediate shows frames as soon as they are done, which can
+                        tear (default fifo)
+    --device <index>    which Vulkan device to use (default 0)
+
+viewer keys:
+    = - / scroll wheel  zoom in and out around the mouse
+    arrow keys          pan, by a whole view with shift held. Dragging with the middle mouse
+                        button, or the left one when no other mode is on, also pans
+    Z                   center the view on the cell under the mouse
+    Home                reset the view
+    W                   toggle the minimap shown in the corner when the view does not show the
+                        whole world. Clicking it centers the view there
+    F11 / Shift+F11     toggle borderless / exclusive fullscreen
+    D                   switch how zoomed out views combine cells: average, majority or nearest
+    , .                 halve and double the generations per frame
+    F                   advance one generation and pause
+    R                   reset the world
+    N                   reset the world with a new seed
+    G                   switch to the next soup generator
+    Space / Return      skip to the next interesting rule / the next one with a nonzero first
+                        coefficient
+    Backspace           go back one rule
+    S / L               save a world snapshot / load the last saved or loaded one
+    E                   export the selection, or the visible part of the world, as an RLE
+                        pattern
+    X                   save the current rule as a Golly .rule file
+    P                   toggle paint mode, where the left mouse button paints and the right one
+                        erases
+    V / B               cycle the value / size of the brush
+    M                   toggle select mode, where the left mouse button drags out a selection and
+                        the right one clears it
+    Ctrl+A / Ctrl+D     select everything visible / nothing
+    Ctrl+C / X / V      copy / cut / paste at the mouse
+    Ctrl+R / Ctrl+Shift+R
+                        rotate the selection clockwise / counterclockwise
+    Ctrl+F / Ctrl+Shift+F
+                        flip the selection left to right / top to bottom
+    Ctrl+N / Delete     randomize / clear the selection
+    Ctrl+S              name the selection and save it as a stamp for the current rule, type the
+                        name and press Return
+    T                   toggle stamp mode, which lists the stamps saved for the current rule and
+                        places the chosen one under the mouse with the left mouse button
+    [ ]                 choose the previous / next stamp
+    Q / Shift+Q         toggle slice mode, where the left mouse button picks a row and the right
+                        one a column to show over time in a space-time pane / close the pane
+    C                   switch to the next palette
+    Tab                 switch between showing cells, heat trails of recent changes, cell age, the
+                        cells which changed in the last generation and the period each cell
+                        repeats with. Chaotic cells are crimson and still lifes gray
+    O                   steady rules whose background flashes, by hiding cells which match the
+                        background of an empty world or by averaging the last two generations
+    H                   toggle the HUD, which shows the rule, generation, speed, population
+                        density and zoom, and the value, neighborhood sum and next value of the
+                        cell under the mouse
+    K / Shift+K         toggle a chart of the population density and how many cells hold each
+                        value over time / save the same numbers as CSV. While the chart is hidden
+                        they are only measured every 256 generations
+    Y / Shift+Y         toggle a second world beside the main one, which starts as a copy of it
+                        and keeps its rule and seed while the main one's change / toggle a third
+                        pane showing the cells where the two worlds differ. Edits only apply to
+                        the main world
+    F12 / Shift+F12     save a PNG of the cells in view, at the view's zoom and offset / of the
+                        whole world at one pixel per cell. Both show plain cell values in the
+                        palette's colors, without the view mode, destrobing, downsampling or
+                        overlays. The rule, seed and generation are saved in text chunks
+    I                   toggle a gallery of every rule one coefficient away from the current one,
+                        running side by side in small worlds. Clicking one switches to it
+    Escape              quit";
+
+fn parse_present_mode(name: &str) -> Result<PresentMode, String> {
+    match name {
+        "fifo" => Ok(PresentMode::Fifo),
+        "mailbox" => Ok(PresentMode::Mailbox),
+        "immediate" => Ok(PresentMode::Immediate),
+        _ => Err(format!(
+            "unknown present mode \"{}\", expected fifo, mailbox or immediate",
+            name
+        )),
+    }
+}
+
+pub enum Command {
+    View {
+        rule: KernelArguments,
+        /// The rule and seed of a world shown beside the main one.
+        compare_rule: Option<Box<KernelArguments>>,
+        compare_seed: Option<u32>,
+    },
+    Search {
+        from: KernelArguments,
+        until: Option<Box<KernelArguments>>,
+    },
+    Score {
+        rule: KernelArguments,
+    },
+    Judge {
+        rule: KernelArguments,
+    },
+    Render {
+        rule: KernelArguments,
+        generations: u32,
+        out: PathBuf,
+    },
+    Golly {
+        rule: KernelArguments,
+    },
+}
+
+impl Command {
+    pub fn is_interactive(&self) -> bool {
+        matches!(self, Self::View { .. })
+    }
+
+    pub fn rule(&self) -> KernelArguments {
+        match self {
+            Self::View { rule, .. }
+            | Self::Score { rule }
+            | Self::Judge { rule }
+            | Self::Render { rule, .. }
+            | Self::Golly { rule } => *rule,
+            Self::Search { from, .. } => *from,
+        }
+    }
+}
+
+pub struct CommonFlags {
+    pub world_size: u32,
+    pub seed: u32,
+    pub soup: Soup,
+    pub output_dir: PathBuf,
+    pub load_image: Option<PathBuf>,
+    pub load_world: Option<PathBuf>,
+    pub load_pattern: Option<PathBuf>,
+    pub save_world: Option<PathBuf>,
+    pub screenshot: Option<PathBuf>,
+    pub stamp_dir: PathBuf,
+    pub image_mapping: ImageMapping,
+    pub palette: Palette,
+    pub present_mode: PresentMode,
+    pub device: usize,
+}
+
+impl Default for CommonFlags {
+    fn default() -> Self {
+        Self {
+            world_size: DEFAULT_WORLD_SIZE,
+            seed: 0,
+            soup: Soup::default(),
+            output_dir: PathBuf::from("captures/9"),
+            load_image: None,
+            load_world: None,
+            load_pattern: None,
+            save_world: None,
+            screenshot: None,
+            stamp_dir: PathBuf::from("stamps"),
+            image_mapping: ImageMapping::Palette,
+            palette: Palette::Classic,
+            present_mode: PresentMode::Fifo,
+            device: 0,
+        }
+    }
+}
+
+pub struct CommandLine {
+    pub command: Command,
+    pub common: CommonFlags,
+}
+
+/// Flags and positional arguments which have not yet been claimed by a command.
+struct RawArguments {
+    positional: Vec<String>,
+    flags: Vec<(String, String)>,
+}
+
+impl RawArguments {
+    fn split(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut positional = Vec::new();
+        let mut flags = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg.starts_with("--") {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("{} needs a value", arg))?;
+                flags.push((arg, value));
+            } else {
+                positional.push(arg);
+            }
+        }
+        Ok(Self { positional, flags })
+    }
+
+    fn take_flag(&mut self, name: &str) -> Option<String> {
+        let index = self.flags.iter().position(|(flag, _)| flag == name)?;
+        Some(self.flags.remove(index).1)
+    }
+
+    fn take_parsed_flag<T: std::str::FromStr>(&mut self, name: &str) -> Result<Option<T>, String> {
+        match self.take_flag(name) {
+            Some(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("\"{}\" is not a valid value for {}", value, name)),
+            None => Ok(None),
+        }
+    }
+
+    fn take_rule_flag(&mut self, name: &str) -> Result<Option<KernelArguments>, String> {
+        self.take_flag(name)
+            .map(|text| rule::parse(&text))
+            .transpose()
+    }
+
+    /// All remaining positional arguments are joined together so that rules can be written
+    /// without quotes, E.G. `view 4 % 0 0 0 1`.
+    fn take_positional_rule(&mut self) -> Result<KernelArguments, String> {
+        if self.positional.is_empty() {
+            return Err("expected a rule".to_string());
+        }
+        let text = self.positional.join(" ");
+        self.positional.clear();
+        rule::parse(&text)
+    }
+
+    fn finish(self) -> Result<(), String> {
+        if let Some((flag, _)) = self.flags.first() {
+            Err(format!("unrecognized flag {}", flag))
+        } else if let Some(arg) = self.positional.first() {
+            Err(format!("unexpected argument \"{}\"", arg))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl CommandLine {
+    pub fn from_env() -> Self {
+        let args: Vec<_> = std::env::args().skip(1).collect();
+        if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+            println!("{}", USAGE);
+            std::process::exit(0);
+        }
+        match Self::parse(args) {
+            Ok(command_line) => command_line,
+            Err(message) => {
+                eprintln!("error: {}\n\n{}", message, USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut raw = RawArguments::split(args)?;
+        let common = Self::parse_common_flags(&mut raw)?;
+        let command = if raw.positional.is_empty() {
+            Command::View {
+                rule: rule::parse("2 % 0 0").unwrap(),
+                compare_rule: None,
+                compare_seed: None,
+            }
+        } else {
+            let name = raw.positional.remove(0);
+            Self::parse_command(&name, &mut raw)?
+        };
+        raw.finish()?;
+        Ok(Self { command, common })
+    }
+
+    fn parse_common_flags(raw: &mut RawArguments) -> Result<CommonFlags, String> {
+        let mut common = CommonFlags::default();
+        if let Some(world_size) = raw.take_parsed_flag::<u32>("--size")? {
+            options::check_world_size(world_size)?;
+            common.world_size = world_size;
+        }
+        if let Some(seed) = raw.take_parsed_flag("--seed")? {
+            common.seed = seed;
+        }
+        if let Some(generator) = raw.take_flag("--generator") {
+            common.soup.generator = generator.parse()?;
+        }
+        if let Some(density) = raw.take_parsed_flag::<f32>("--density")? {
+            if !(0.0..=1.0).contains(&density) {
+                return Err("the density must be between 0 and 1".to_string());
+            }
+            common.soup.density = density;
+        }
+        if let Some(values) = raw.take_flag("--values") {
+            let values = values.parse()?;
+            if let ValueRange::Fixed(max) = values {
+                if max > u16::MAX as u32 {
+                    return Err(format!("cells cannot hold values above {}", u16::MAX));
+                }
+            }
+            common.soup.values = values;
+        }
+        if let Some(feature_size) = raw.take_parsed_flag("--feature-size")? {
+            common.soup.feature_size = feature_size;
+        }
+        if let Some(octaves) = raw.take_parsed_flag::<u32>("--octaves")? {
+            if !(1..=4).contains(&octaves) {
+                return Err("there must be between 1 and 4 octaves".to_string());
+            }
+            common.soup.octaves = octaves;
+        }
+        if let Some(output_dir) = raw.take_flag("--output-dir") {
+            common.output_dir = PathBuf::from(output_dir);
+        }
+        common.load_image = raw.take_flag("--load-image").map(PathBuf::from);
+        common.load_world = raw.take_flag("--load-world").map(PathBuf::from);
+        common.load_pattern = raw.take_flag("--load-pattern").map(PathBuf::from);
+        common.save_world = raw.take_flag("--save-world").map(PathBuf::from);
+        common.screenshot = raw.take_flag("--screenshot").map(PathBuf::from);
+        if let Some(stamp_dir) = raw.take_flag("--stamp-dir") {
+            common.stamp_dir = PathBuf::from(stamp_dir);
+        }
+        if let Some(mapping) = raw.take_flag("--image-mapping") {
+            common.image_mapping = mapping.parse()?;
+        }
+        if let Some(palette) = raw.take_flag("--palette") {
+            common.palette = Palette::from_argument(&palette)?;
+        }
+        if let Some(present_mode) = raw.take_flag("--present-mode") {
+            common.present_mode = parse_present_mode(&present_mode)?;
+        }
+        if let Some(device) = raw.take_parsed_flag("--device")? {
+            common.device = device;
+        }
+        Ok(common)
+    }
+
+    fn parse_command(name: &str, raw: &mut RawArguments) -> Result<Command, String> {
+        Ok(match name {
+            "view" => Command::View {
+                compare_rule: raw.take_rule_flag("--compare")?.map(Box::new),
+                compare_seed: raw.take_parsed_flag("--compare-seed")?,
+                rule: raw.take_positional_rule()?,
+            },
+            "search" => {
+                let from = raw
+                    .take_rule_flag("--from")?
+                    .ok_or_else(|| "search needs a --from rule".to_string())?;
+                let until = raw.take_rule_flag("--until")?.map(Box::new);
+                if let Some(until) = &until {
+                    if until[0] != from[0] {
+                        return Err("--from and --until must have the same divisor".to_string());
+                    }
+                }
+                Command::Search { from, until }
+            }
+            "score" => Command::Score {
+                rule: raw.take_positional_rule()?,
+            },
+            "judge" => Command::Judge {
+                rule: raw.take_positional_rule()?,
+            },
+            "render" => {
+                let generations = raw
+                    .take_parsed_flag("--generations")?
+                    .ok_or_else(|| "render needs --generations".to_string())?;
+                let out = raw
+                    .take_flag("--out")
+                    .ok_or_else(|| "render needs an --out file".to_string())?;
+                Command::Render {
+                    rule: raw.take_positional_rule()?,
+                    generations,
+                    out: PathBuf::from(out),
+                }
+            }
+            "golly" => Command::Golly {
+                rule: raw.take_positional_rule()?,
+            },
+            other => return Err(format!("unknown command \"{}\"", other)),
+        })
+    }
+}
@@ -1,5 +1,6 @@
 use vulkano::{command_buffer::CommandBuffer, framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract}};
-use vulkano::image::SwapchainImage;
+use vulkano::format::Format;
+use vulkano::image::{StorageImage, SwapchainImage};
 use vulkano::pipeline::viewport::Viewport;
 use vulkano::swapchain::{self, AcquireError, Swapchain, SwapchainCreationError};
 use vulkano::sync::{FlushError, GpuFuture};
@@ -196,6 +197,24 @@ impl DispatchManager {
         true
     }
 
+    /// Replaces the presenter with one whose image is `resolution` pixels, and returns that image
+    /// for the renderer to draw into.
+    pub fn resize_presented_image(&mut self, resolution: (u32, u32)) -> Arc<StorageImage<Format>> {
+        self.presenter = Arc::new(Presenter::new(
+            self.device.clone(),
+            self.queue.clone(),
+            resolution,
+            self.swapchain.format(),
+        ));
+        // The framebuffers belong to the old presenter's render pass.
+        self.recreate_swapchain = true;
+        self.presenter.get_presented_image()
+    }
+
+    pub fn window(&self) -> &Window {
+        self.surface.window()
+    }
+
     pub fn invalidate_swapchain(&mut self) {
         self.recreate_swapchain = true;
     }
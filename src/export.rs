@@ -0,0 +1,114 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use crate::palette::{self, Color};
+
+/// Saves 8 bit RGB `pixels` as a PNG with each entry of `text` in a tEXt chunk, E.G. the rule and
+/// generation, so that the image says what it shows. The image crate cannot write text chunks,
+/// so this uses the PNG encoder it is built on.
+pub fn save_png(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    text: &[(&str, String)],
+    filename: &Path,
+) -> std::io::Result<()> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(filename)?), width, height);
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    for (keyword, value) in text {
+        // The keyword and the text are separated by a null byte.
+        let mut chunk = keyword.as_bytes().to_vec();
+        chunk.push(0);
+        chunk.extend_from_slice(value.as_bytes());
+        writer.write_chunk(*b"tEXt", &chunk)?;
+    }
+    writer.write_image_data(pixels)?;
+    Ok(())
+}
+
+/// Saves the whole world at one pixel per cell, colored with `colors` from `Palette::colors`.
+pub fn save_world_png(
+    world: &[u16],
+    world_size: u32,
+    colors: &[Color],
+    text: &[(&str, String)],
+    filename: &Path,
+) -> std::io::Result<()> {
+    let mut pixels = Vec::with_capacity(world.len() * 3);
+    for &cell in world {
+        pixels.extend_from_slice(&palette::cell_color(colors, cell));
+    }
+    save_png(&pixels, world_size, world_size, text, filename)
+}
+
+/// The RGB pixels of the cells in a `width` by `height` view of the world with `offset` in its
+/// top left corner and `zoom` pixels per cell. This is a plain view of the cell values: each pixel
+/// is the palette color of the cell under its top left corner, whatever the view mode, destrobing
+/// or downsampling on screen.
+pub fn cell_view_pixels(
+    world: &[u16],
+    world_size: u32,
+    colors: &[Color],
+    offset: [f32; 2],
+    zoom: f32,
+    (width, height): (u32, u32),
+) -> Vec<u8> {
+    let wrap = |position: f32| (position.floor() as i64).rem_euclid(world_size as i64) as usize;
+    let mut pixels = Vec::with_capacity((width * height * 3) as usize);
+    for y in 0..height {
+        let row = wrap(y as f32 / zoom + offset[1]) * world_size as usize;
+        for x in 0..width {
+            let cell = world[row + wrap(x as f32 / zoom + offset[0])];
+            pixels.extend_from_slice(&palette::cell_color(colors, cell));
+        }
+    }
+    pixels
+}
+
+/// Writes a sidecar file next to a capture recording everything needed to reproduce it, one
+/// `key = value` pair per line.
+pub fn write_metadata(capture: &Path, entries: &[(&str, String)]) -> std::io::Result<()> {
+    let mut contents = String::new();
+    for (key, value) in entries {
+        contents.push_str(&format!("{} = {}\n", key, value));
+    }
+    std::fs::write(capture.with_extension("txt"), contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLORS: [Color; 3] = [[0, 0, 0], [255, 0, 0], [0, 0, 255]];
+
+    #[test]
+    fn cell_views_zoom_and_wrap() {
+        // 1 2
+        // 0 0
+        let world = [1, 2, 0, 0];
+        let pixels = cell_view_pixels(&world, 2, &COLORS, [-0.5, 0.0], 2.0, (4, 1));
+        let cells: Vec<_> = pixels
+            .chunks(3)
+            .map(|pixel| COLORS.iter().position(|color| color == pixel).unwrap())
+            .collect();
+        assert_eq!(cells, vec![2, 1, 1, 2]);
+    }
+
+    #[test]
+    fn pngs_hold_their_text() {
+        let filename = std::env::temp_dir().join(format!("export-test-{}.png", std::process::id()));
+        let text = [("rule", "4 % 0 0 0 1".to_string())];
+        save_world_png(&[0, 1, 2, 1], 2, &COLORS, &text, &filename).unwrap();
+        let bytes = std::fs::read(&filename).unwrap();
+        let (info, mut reader) = png::Decoder::new(bytes.as_slice()).read_info().unwrap();
+        let mut pixels = vec![0; info.buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+        std::fs::remove_file(&filename).unwrap();
+
+        assert_eq!((info.width, info.height), (2, 2));
+        assert_eq!(&pixels[3..9], &[255, 0, 0, 0, 0, 255]);
+        let chunk = b"tEXtrule\x004 % 0 0 0 1";
+        assert!(bytes.windows(chunk.len()).any(|window| window == chunk));
+    }
+}
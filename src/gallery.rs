@@ -0,0 +1,174 @@
+//! A grid of small worlds running every rule one coefficient away from the current one, see
+//! `rule::neighbors`. The rule keys only step through the rule space like an odometer, this shows
+//! every direction at once.
+
+use std::sync::Arc;
+
+use vulkano::{format::Format, image::StorageImage};
+
+use crate::{
+    dispatch_manager::DispatchManager,
+    hud::HudImage,
+    options::{Destrobe, Options, Pane},
+    renderer::Renderer,
+    rule::{self, KernelArguments},
+};
+
+/// Cells along each side of a thumbnail's world.
+const THUMBNAIL_WORLD_SIZE: u32 = 128;
+/// Rules with large divisors have hundreds of neighbors, which would take long to set up and be
+/// too small to see.
+const MAX_THUMBNAILS: usize = 64;
+/// Image pixels left black between thumbnails.
+const GAP: u32 = 2;
+
+struct Thumbnail {
+    renderer: Renderer,
+    kernel_arguments: KernelArguments,
+    pane: Pane,
+}
+
+pub struct Gallery {
+    /// The rule the thumbnails are neighbors of.
+    center: KernelArguments,
+    thumbnails: Vec<Thumbnail>,
+    /// How many generations have passed since the thumbnails were last reset.
+    generation: u64,
+    /// New worlds are empty until they are reset.
+    reset_pending: bool,
+}
+
+impl Gallery {
+    /// The thumbnails draw into the same image as `beside`.
+    pub fn new(beside: &Renderer, center: KernelArguments) -> Self {
+        let mut neighbors = rule::neighbors(&center);
+        if neighbors.len() > MAX_THUMBNAILS {
+            println!(
+                "Showing the first {} of {} neighboring rules",
+                MAX_THUMBNAILS,
+                neighbors.len()
+            );
+            neighbors.truncate(MAX_THUMBNAILS);
+        }
+        let thumbnails = neighbors
+            .into_iter()
+            .map(|kernel_arguments| {
+                let mut renderer = beside.new_beside(THUMBNAIL_WORLD_SIZE);
+                let mut label = HudImage::new();
+                label.text_panel(0, &[rule::notation(&kernel_arguments)]);
+                renderer.set_hud(&label.pixels);
+                Thumbnail {
+                    renderer,
+                    kernel_arguments,
+                    pane: Pane {
+                        left: 0,
+                        top: 0,
+                        width: 0,
+                        height: 0,
+                    },
+                }
+            })
+            .collect();
+        let mut gallery = Self {
+            center,
+            thumbnails,
+            generation: 0,
+            reset_pending: true,
+        };
+        gallery.layout(beside.target_size());
+        gallery
+    }
+
+    pub fn center(&self) -> &KernelArguments {
+        &self.center
+    }
+
+    /// Draws into a new image from now on, E.G. after the window was resized.
+    pub fn set_target_image(&mut self, image: Arc<StorageImage<Format>>) {
+        for thumbnail in &mut self.thumbnails {
+            thumbnail.renderer.set_target_image(image.clone());
+        }
+        let size = match self.thumbnails.first() {
+            Some(thumbnail) => thumbnail.renderer.target_size(),
+            None => return,
+        };
+        self.layout(size);
+    }
+
+    /// Arranges the thumbnails in a grid of the largest squares which fit in an image of `width`
+    /// by `height`.
+    fn layout(&mut self, (width, height): (u32, u32)) {
+        let count = self.thumbnails.len().max(1) as u32;
+        let (columns, size) = (1..=count)
+            .map(|columns| {
+                let rows = (count - 1) / columns + 1;
+                (columns, (width / columns).min(height / rows))
+            })
+            .max_by_key(|&(_, size)| size)
+            .unwrap();
+        for (index, thumbnail) in self.thumbnails.iter_mut().enumerate() {
+            let index = index as u32;
+            thumbnail.pane = Pane {
+                left: index % columns * size,
+                top: index / columns * size,
+                width: size.saturating_sub(GAP).max(1),
+                height: size.saturating_sub(GAP).max(1),
+            };
+        }
+    }
+
+    /// The rule of the thumbnail at a pixel of the image, if there is one there.
+    pub fn rule_at(&self, x: f32, y: f32) -> Option<KernelArguments> {
+        self.thumbnails
+            .iter()
+            .find(|thumbnail| thumbnail.pane.contains(x, y))
+            .map(|thumbnail| thumbnail.kernel_arguments)
+    }
+
+    /// Advances and draws every thumbnail. They follow the main options, E.G. the rate, seed and
+    /// palette, but each shows its own rule and the whole of its world.
+    pub fn render(&mut self, dispatcher: &mut DispatchManager, main: &Options) -> bool {
+        let reset = main.reset || self.reset_pending;
+        self.reset_pending = false;
+        if reset {
+            self.generation = 0;
+        }
+        self.generation += (main.rate + main.skip) as u64;
+        let generation = self.generation;
+        let options: Vec<Options> = self
+            .thumbnails
+            .iter()
+            .map(|thumbnail| {
+                let mut options = Options {
+                    kernel_arguments: thumbnail.kernel_arguments,
+                    offset: [0.0, 0.0],
+                    zoom: thumbnail.pane.width as f32
+                        / THUMBNAIL_WORLD_SIZE as f32
+                        / main.scale_factor,
+                    selection: None,
+                    hud: true,
+                    chart: false,
+                    slice: None,
+                    minimap: false,
+                    pane: Some(thumbnail.pane),
+                    difference_pane: None,
+                    reset,
+                    display: true,
+                    ..main.clone()
+                };
+                if options.destrobe == Destrobe::Background {
+                    options.background =
+                        rule::background_value(&thumbnail.kernel_arguments, generation);
+                }
+                options
+            })
+            .collect();
+        let worlds = self
+            .thumbnails
+            .iter_mut()
+            .map(|thumbnail| &mut thumbnail.renderer)
+            .zip(&options)
+            .collect();
+        Renderer::render_together(dispatcher, worlds)
+    }
+}
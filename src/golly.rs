@@ -0,0 +1,179 @@
+//! Exports rules as Golly RuleLoader `.rule` files.
+
+use std::path::Path;
+
+use crate::{
+    palette::{self, Palette},
+    rule::{self, KernelArguments},
+};
+
+/// The most states a Golly rule can have.
+pub const MAX_STATES: usize = 256;
+
+/// Golly looks rules up by filename, so the name avoids characters which are awkward in paths.
+pub fn rule_name(arguments: &KernelArguments) -> String {
+    format!("SSM_{}", rule::filename_part(arguments).replace('-', "_"))
+}
+
+/// Every value a cell can hold after the first generation is one of the coefficients, and Golly
+/// needs states to be numbered contiguously from zero.
+pub fn state_count(arguments: &KernelArguments) -> usize {
+    let largest = rule::coefficients(arguments)
+        .iter()
+        .copied()
+        .max()
+        .unwrap_or(0);
+    (largest as usize + 1).max(2)
+}
+
+/// The rule as a decision tree. A table would need a line for every combination of neighbor
+/// states, but since only the sum of the neighborhood matters, each level of the tree only has
+/// to remember the running sum modulo the divisor. That keeps the tree at 8 * divisor + 1 nodes.
+fn write_tree(arguments: &KernelArguments, states: usize) -> String {
+    let divisor = rule::divisor(arguments);
+    let coefficients = rule::coefficients(arguments);
+    let mut nodes = Vec::new();
+    // Level 1 nodes pick the new state once the last cell has been added to the sum.
+    for sum in 0..divisor {
+        let children: Vec<_> = (0..states)
+            .map(|state| coefficients[(sum + state) % divisor].to_string())
+            .collect();
+        nodes.push(format!("1 {}", children.join(" ")));
+    }
+    // Levels 2 to 8 point at the node one level down which holds the new running sum. Nodes for
+    // level n start at index (n - 1) * divisor.
+    for level in 2..=8 {
+        for sum in 0..divisor {
+            let children: Vec<_> = (0..states)
+                .map(|state| ((level - 2) * divisor + (sum + state) % divisor).to_string())
+                .collect();
+            nodes.push(format!("{} {}", level, children.join(" ")));
+        }
+    }
+    // The root starts with a sum of zero.
+    let children: Vec<_> = (0..states)
+        .map(|state| (7 * divisor + state % divisor).to_string())
+        .collect();
+    nodes.push(format!("9 {}", children.join(" ")));
+
+    format!(
+        "@TREE\n\nnum_states={}\nnum_neighbors=8\nnum_nodes={}\n{}\n",
+        states,
+        nodes.len(),
+        nodes.join("\n")
+    )
+}
+
+/// Uses the same colors as the viewer.
+fn write_colors(arguments: &KernelArguments, palette: &Palette, states: usize) -> String {
+    let palette_colors = palette.colors(rule::largest_value(arguments));
+    let mut colors = String::from("@COLORS\n\n");
+    for state in 0..states {
+        let [r, g, b] = palette::cell_color(&palette_colors, state as u16);
+        colors.push_str(&format!("{} {} {} {}\n", state, r, g, b));
+    }
+    colors
+}
+
+/// Fails when the rule has more states than Golly supports.
+pub fn write_rule_file(arguments: &KernelArguments, palette: &Palette) -> Result<String, String> {
+    let states = state_count(arguments);
+    if states > MAX_STATES {
+        return Err(format!(
+            "the rule {} has {} states, Golly supports at most {}",
+            rule::notation(arguments),
+            states,
+            MAX_STATES
+        ));
+    }
+    Ok(format!(
+        "@RULE {}\n\nSquare Sum Map rule {}: every cell becomes the coefficient picked by the sum \
+         of its 3x3 neighborhood, itself included, modulo the divisor.\n\n{}\n{}",
+        rule_name(arguments),
+        rule::notation(arguments),
+        write_tree(arguments, states),
+        write_colors(arguments, palette, states)
+    ))
+}
+
+/// Saves the rule file into `directory` under the name Golly expects and prints where it went.
+/// Returns false and prints why if it could not be saved.
+pub fn save_rule_file(arguments: &KernelArguments, palette: &Palette, directory: &Path) -> bool {
+    let contents = match write_rule_file(arguments, palette) {
+        Ok(contents) => contents,
+        Err(message) => {
+            eprintln!("Could not export the rule: {}", message);
+            return false;
+        }
+    };
+    let filename = directory.join(format!("{}.rule", rule_name(arguments)));
+    match std::fs::create_dir_all(directory).and_then(|()| std::fs::write(&filename, contents)) {
+        Ok(()) => {
+            println!("{}", filename.display());
+            true
+        }
+        Err(err) => {
+            eprintln!("Could not save {}: {}", filename.display(), err);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_states_from_the_largest_coefficient() {
+        assert_eq!(state_count(&rule::parse("4 % 0 0 0 1").unwrap()), 2);
+        assert_eq!(state_count(&rule::parse("3 % 0 7 2").unwrap()), 8);
+        assert_eq!(state_count(&rule::parse("2 % 0 0").unwrap()), 2);
+    }
+
+    #[test]
+    fn tree_follows_the_rule() {
+        let arguments = rule::parse("3 % 0 2 1").unwrap();
+        let tree = write_tree(&arguments, 3);
+        let nodes: Vec<Vec<usize>> = tree
+            .lines()
+            .filter(|line| line.starts_with(char::is_numeric))
+            .map(|line| line.split(' ').map(|n| n.parse().unwrap()).collect())
+            .collect();
+        assert_eq!(nodes.len(), 8 * 3 + 1);
+        assert!(tree.contains(&format!("num_nodes={}", nodes.len())));
+        // Walks the tree from the root the way Golly does, one cell of the neighborhood per level.
+        let next = |cells: [usize; 9]| {
+            let mut node = nodes.len() - 1;
+            for &cell in &cells[..8] {
+                node = nodes[node][1 + cell];
+            }
+            nodes[node][1 + cells[8]]
+        };
+        for cells in &[
+            [0; 9],
+            [1, 0, 0, 0, 0, 0, 0, 0, 1],
+            [2, 2, 1, 0, 0, 0, 0, 0, 0],
+        ] {
+            let sum: usize = cells.iter().sum();
+            assert_eq!(next(*cells), arguments[1 + sum % 3] as usize);
+        }
+    }
+
+    #[test]
+    fn rejects_rules_with_too_many_states() {
+        let arguments = rule::parse("2 % 0 300").unwrap();
+        assert!(write_rule_file(&arguments, &Palette::Classic).is_err());
+        let arguments = rule::parse("2 % 0 255").unwrap();
+        assert!(write_rule_file(&arguments, &Palette::Classic).is_ok());
+    }
+
+    #[test]
+    fn reports_directories_which_cannot_be_created() {
+        let file = std::env::temp_dir().join(format!("golly-test-{}", std::process::id()));
+        std::fs::write(&file, "").unwrap();
+        let arguments = rule::parse("4 % 0 0 0 1").unwrap();
+        let saved = save_rule_file(&arguments, &Palette::Classic, &file.join("rules"));
+        std::fs::remove_file(&file).unwrap();
+        assert!(!saved);
+    }
+}
@@ -0,0 +1,225 @@
+//! The overlay drawn over the top left corner of the viewer, holding the HUD text and the chart
+//! from chart.rs. It is rasterized here into a small image which finalize.comp scales up and
+//! draws over the world.
+
+use std::time::Instant;
+
+use crate::rule::{self, KernelArguments};
+
+/// The size of the overlay image in HUD pixels. Anything which does not fit is cut off.
+pub const HUD_WIDTH: u32 = 320;
+pub const HUD_HEIGHT: u32 = 160;
+/// Values of HUD pixels. Matches the HUD constants in finalize.comp. Pixels from `HUD_VALUE` up
+/// are drawn in the palette color of the value `pixel - HUD_VALUE`.
+pub const HUD_CLEAR: u16 = 0;
+pub const HUD_PANEL: u16 = 1;
+pub const HUD_TEXT: u16 = 2;
+pub const HUD_VALUE: u16 = 3;
+
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+const ADVANCE: usize = GLYPH_WIDTH + 1;
+pub const LINE_HEIGHT: usize = GLYPH_HEIGHT + 2;
+pub const MARGIN: usize = 2;
+/// Screen pixels per HUD pixel at a scale factor of one.
+const HUD_ZOOM: u32 = 2;
+/// How often the speeds are worked out, in seconds.
+const METER_INTERVAL: f32 = 0.5;
+
+/// Rows from top to bottom, with the leftmost column in the highest bit. Lowercase letters are
+/// drawn as uppercase and anything else missing from here as a space.
+#[rustfmt::skip]
+const FONT: [(char, [u8; GLYPH_HEIGHT]); 46] = [
+    ('0', [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110]),
+    ('1', [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('2', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111]),
+    ('3', [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110]),
+    ('4', [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010]),
+    ('5', [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110]),
+    ('6', [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110]),
+    ('7', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000]),
+    ('8', [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110]),
+    ('9', [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100]),
+    ('A', [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('B', [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110]),
+    ('C', [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110]),
+    ('D', [0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110]),
+    ('E', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111]),
+    ('F', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('G', [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111]),
+    ('H', [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('I', [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('J', [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100]),
+    ('K', [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001]),
+    ('L', [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111]),
+    ('M', [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001]),
+    ('N', [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001]),
+    ('O', [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('P', [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('Q', [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101]),
+    ('R', [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001]),
+    ('S', [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110]),
+    ('T', [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('U', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('V', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100]),
+    ('W', [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010]),
+    ('X', [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001]),
+    ('Y', [0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('Z', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111]),
+    ('.', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100]),
+    (',', [0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000]),
+    (':', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000]),
+    ('%', [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011]),
+    ('/', [0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000]),
+    ('-', [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000]),
+    ('+', [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000]),
+    ('=', [0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000]),
+    ('(', [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010]),
+    (')', [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000]),
+
+];
+
+fn glyph(c: char) -> Option<&'static [u8; GLYPH_HEIGHT]> {
+    let c = c.to_ascii_uppercase();
+    FONT.iter().find(|(key, _)| *key == c).map(|(_, rows)| rows)
+}
+
+/// What the HUD shows, HUD_WIDTH * HUD_HEIGHT pixels in rows from the top.
+pub struct HudImage {
+    pub pixels: Vec<u16>,
+}
+
+impl HudImage {
+    pub fn new() -> Self {
+        Self {
+            pixels: vec![HUD_CLEAR; (HUD_WIDTH * HUD_HEIGHT) as usize],
+        }
+    }
+
+    /// Pixels outside the image are ignored.
+    pub fn set(&mut self, x: usize, y: usize, pixel: u16) {
+        if x < HUD_WIDTH as usize && y < HUD_HEIGHT as usize {
+            self.pixels[y * HUD_WIDTH as usize + x] = pixel;
+        }
+    }
+
+    pub fn fill(&mut self, left: usize, top: usize, width: usize, height: usize, pixel: u16) {
+        for y in top..top + height {
+            for x in left..left + width {
+                self.set(x, y, pixel);
+            }
+        }
+    }
+
+    /// Draws one line of text with its top left corner at `left`, `top`.
+    pub fn text(&mut self, left: usize, top: usize, text: &str) {
+        for (index, c) in text.chars().enumerate() {
+            let rows = match glyph(c) {
+                Some(rows) => rows,
+                None => continue,
+            };
+            let glyph_left = left + index * ADVANCE;
+            for (dy, row) in rows.iter().enumerate() {
+                for dx in 0..GLYPH_WIDTH {
+                    if row & (1 << (GLYPH_WIDTH - 1 - dx)) != 0 {
+                        self.set(glyph_left + dx, top + dy, HUD_TEXT);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draws lines of text on a panel just big enough to hold them, starting at `top`. Returns
+    /// the row below the panel.
+    pub fn text_panel(&mut self, top: usize, lines: &[String]) -> usize {
+        let longest = lines.iter().map(|line| line.chars().count()).max();
+        let width = match longest {
+            Some(longest) if longest > 0 => longest * ADVANCE + MARGIN * 2 - 1,
+            _ => return top,
+        };
+        let height = lines.len() * LINE_HEIGHT + MARGIN * 2 - 2;
+        self.fill(0, top, width, height, HUD_PANEL);
+        for (index, line) in lines.iter().enumerate() {
+            self.text(MARGIN, top + MARGIN + index * LINE_HEIGHT, line);
+        }
+        top + height
+    }
+}
+
+/// Screen pixels per HUD pixel, so that the text stays the same size on high DPI monitors.
+pub fn scale(scale_factor: f32) -> u32 {
+    scale_factor.round().max(1.0) as u32 * HUD_ZOOM
+}
+
+/// What the inspector shows about one cell.
+pub struct CellReport {
+    pub value: u16,
+    /// The sum of the cell and its eight neighbors, before it is wrapped by the divisor.
+    pub neighborhood: u32,
+    /// The value the cell will have next generation.
+    pub next: u16,
+}
+
+impl CellReport {
+    /// Works out the next value the same way simulate.comp does. `x` and `y` are wrapped around
+    /// the edges of the world.
+    pub fn of(world: &[u16], world_size: u32, arguments: &KernelArguments, x: i32, y: i32) -> Self {
+        let size = world_size as i32;
+        let sample = |dx: i32, dy: i32| {
+            let sx = (x + dx).rem_euclid(size) as usize;
+            let sy = (y + dy).rem_euclid(size) as usize;
+            world[sy * world_size as usize + sx]
+        };
+        let mut neighborhood = 0;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                neighborhood += sample(dx, dy) as u32;
+            }
+        }
+        Self {
+            value: sample(0, 0),
+            neighborhood,
+            next: rule::next_value(arguments, neighborhood),
+        }
+    }
+}
+
+/// Counts frames and generations and turns them into rates every `METER_INTERVAL` seconds.
+pub struct SpeedMeter {
+    since: Instant,
+    frames: u32,
+    generations: u64,
+    pub frames_per_second: f32,
+    pub generations_per_second: f32,
+}
+
+impl SpeedMeter {
+    pub fn new() -> Self {
+        Self {
+            since: Instant::now(),
+            frames: 0,
+            generations: 0,
+            frames_per_second: 0.0,
+            generations_per_second: 0.0,
+        }
+    }
+
+    pub fn add_generations(&mut self, generations: u64) {
+        self.generations += generations;
+    }
+
+    /// Returns true when the rates were just updated.
+    pub fn add_frame(&mut self) -> bool {
+        self.frames += 1;
+        let elapsed = self.since.elapsed().as_secs_f32();
+        if elapsed < METER_INTERVAL {
+            return false;
+        }
+        self.frames_per_second = self.frames as f32 / elapsed;
+        self.generations_per_second = self.generations as f32 / elapsed;
+        self.since = Instant::now();
+        self.frames = 0;
+        self.generations = 0;
+        true
+    }
+}
@@ -0,0 +1,81 @@
+use std::{path::Path, str::FromStr};
+
+use crate::palette::Color;
+
+/// How the pixels of an image are turned into cell values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageMapping {
+    /// Each pixel becomes the value whose on-screen color is closest to it.
+    Palette,
+    /// Black is zero, white is the largest value and everything in between is spread evenly.
+    Grayscale,
+}
+
+impl FromStr for ImageMapping {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name {
+            "palette" => Ok(Self::Palette),
+            "grayscale" => Ok(Self::Grayscale),
+            _ => Err(format!(
+                "unknown image mapping \"{}\", expected palette or grayscale",
+                name
+            )),
+        }
+    }
+}
+
+/// `palette` holds the on-screen color of every value the pixel may map to.
+fn closest_value(pixel: [u8; 3], palette: &[[u8; 3]]) -> u16 {
+    let distance = |color: &[u8; 3]| {
+        (0..3)
+            .map(|channel| {
+                let difference = color[channel] as i32 - pixel[channel] as i32;
+                difference * difference
+            })
+            .sum::<i32>()
+    };
+    (0..palette.len())
+        .min_by_key(|&value| distance(&palette[value]))
+        .unwrap() as u16
+}
+
+fn grayscale_value(pixel: [u8; 3], max_value: u16) -> u16 {
+    let luma = (pixel[0] as u32 * 299 + pixel[1] as u32 * 587 + pixel[2] as u32 * 114) / 1000;
+    ((luma * max_value as u32 + 127) / 255) as u16
+}
+
+/// Loads an image as a world, centered and cropped to fit. `max_value` is the largest value any
+/// pixel can map to, cells outside the image are zero. The palette mapping matches pixels against
+/// `colors`, which should be the palette the world is shown in.
+pub fn load_world_image(
+    filename: &Path,
+    world_size: u32,
+    mapping: ImageMapping,
+    max_value: u16,
+    colors: &[Color],
+) -> Result<Vec<u16>, String> {
+    let image = image::open(filename)
+        .map_err(|err| format!("Could not load {}: {}", filename.display(), err))?
+        .to_rgb();
+    let palette = &colors[..=(max_value as usize).min(colors.len() - 1)];
+    let mut world = vec![0; (world_size * world_size) as usize];
+    let (width, height) = image.dimensions();
+    // Offsets of the image inside the world, negative when the image is bigger than the world.
+    let left = (world_size as i64 - width as i64) / 2;
+    let top = (world_size as i64 - height as i64) / 2;
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let wx = x as i64 + left;
+        let wy = y as i64 + top;
+        if wx < 0 || wy < 0 || wx >= world_size as i64 || wy >= world_size as i64 {
+            continue;
+        }
+        let value = match mapping {
+            ImageMapping::Palette => closest_value(pixel.0, palette),
+            ImageMapping::Grayscale => grayscale_value(pixel.0, max_value),
+        };
+        world[(wy * world_size as i64 + wx) as usize] = value;
+    }
+    Ok(world)
+}
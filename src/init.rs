@@ -9,7 +9,7 @@ use vulkano::{
 use vulkano_win::VkSurfaceBuild;
 
 use winit::{
-    dpi::PhysicalSize,
+    dpi::LogicalSize,
     event_loop::EventLoop,
     window::{Window, WindowBuilder},
 };
@@ -25,15 +25,29 @@ pub struct InitResult {
     pub swapchain_images: Vec<Arc<SwapchainImage<Window>>>,
 }
 
-pub fn init() -> InitResult {
+/// `visible` is false for the headless commands, which still need a surface for the device to be
+/// created but never present anything to it. Falls back to Fifo if `present_mode` is not
+/// supported.
+pub fn init(device_index: usize, visible: bool, present_mode: PresentMode) -> InitResult {
     let instance = {
         // We don't need anything fancy.
         let extensions = vulkano_win::required_extensions();
         Instance::new(None, &extensions, None).unwrap()
     };
 
-    // Get the first physical device we find.
-    let physical = PhysicalDevice::enumerate(&instance).next().unwrap();
+    let physical = match PhysicalDevice::from_index(&instance, device_index) {
+        Some(physical) => physical,
+        None => {
+            eprintln!(
+                "There is no device with index {}. Available devices:",
+                device_index
+            );
+            for physical in PhysicalDevice::enumerate(&instance) {
+                eprintln!("{}: {}", physical.index(), physical.name());
+            }
+            std::process::exit(1);
+        }
+    };
     println!(
         "Using device: {} (type: {:?})",
         physical.name(),
@@ -43,7 +57,8 @@ pub fn init() -> InitResult {
     // Setup the window.
     let events_loop = EventLoop::new();
     let surface = WindowBuilder::new()
-        .with_inner_size(PhysicalSize::new(1024, 1024))
+        .with_inner_size(LogicalSize::new(1024, 1024))
+        .with_visible(visible)
         .build_vk_surface(&events_loop, instance.clone())
         .unwrap();
     let window = surface.window();
@@ -88,6 +103,16 @@ pub fn init() -> InitResult {
         let dimensions = window.inner_size();
         let dimensions = [dimensions.width, dimensions.height];
         let initial_dimensions = dimensions;
+        // Fifo is the only mode every device has to support.
+        let present_mode = if caps.present_modes.supports(present_mode) {
+            present_mode
+        } else {
+            println!(
+                "{:?} presentation is not supported, using Fifo instead.",
+                present_mode
+            );
+            PresentMode::Fifo
+        };
 
         Swapchain::new(
             device.clone(),
@@ -100,8 +125,10 @@ pub fn init() -> InitResult {
             &queue,
             SurfaceTransform::Identity,
             alpha,
-            PresentMode::Fifo,
-            vulkano::swapchain::FullscreenExclusive::Disallowed,
+            present_mode,
+            // Lets the driver take exclusive control of the display when the window is made
+            // exclusive fullscreen.
+            vulkano::swapchain::FullscreenExclusive::Default,
             true,
             ColorSpace::SrgbNonLinear,
         )
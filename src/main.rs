@@ -1,12 +1,46 @@
 mod app;
+mod brush;
+mod chart;
+mod cli;
 mod dispatch_manager;
+mod export;
+mod gallery;
+mod golly;
+mod hud;
+mod import;
 mod init;
 mod options;
+mod palette;
+mod pattern;
 mod presenter;
 mod renderer;
+mod rle;
+mod rule;
+mod selection;
 mod shaders;
+mod snapshot;
+mod soup;
+mod stamps;
 mod stats;
 
 fn main() {
-    app::App::new().start();
+    let command_line = cli::CommandLine::from_env();
+    // Exporting a rule does not need the GPU.
+    if let cli::Command::Golly { rule } = &command_line.command {
+        if !golly::save_rule_file(
+            rule,
+            &command_line.common.palette,
+            &command_line.common.output_dir,
+        ) {
+            std::process::exit(1);
+        }
+        return;
+    }
+    let app = app::App::new(&command_line);
+    if command_line.command.is_interactive() {
+        app.start();
+    } else {
+        let common = command_line.common;
+        app.run_headless(command_line.command, common.save_world, common.screenshot);
+    }
 }
@@ -1,11 +1,211 @@
-pub const WORLD_SIZE: u32 = 1024;
+use crate::{palette::Palette, selection::Selection, soup::Soup};
+
+pub const DEFAULT_WORLD_SIZE: u32 = 1024;
 pub const PARAMETER_SPACE: usize = 128;
 
+/// The shaders work on 8 by 8 blocks of cells, so worlds must be made of whole blocks.
+pub fn check_world_size(world_size: u32) -> Result<(), String> {
+    if world_size == 0 || world_size % 8 != 0 {
+        return Err("the world size must be a positive multiple of 8".to_string());
+    }
+    Ok(())
+}
+
+/// How a pixel which covers several cells is colored when zoomed out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Downsampling {
+    /// The cell in the pixel's top left corner.
+    Nearest,
+    /// The average color of the cells.
+    Average,
+    /// The most common value among the cells.
+    Majority,
+}
+
+impl Downsampling {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Nearest => "nearest",
+            Self::Average => "average",
+            Self::Majority => "majority",
+        }
+    }
+
+    /// Matches the DOWNSAMPLING constants in finalize.comp.
+    pub fn index(self) -> u32 {
+        self as u32
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Nearest => Self::Average,
+            Self::Average => Self::Majority,
+            Self::Majority => Self::Nearest,
+        }
+    }
+}
+
+/// What finalize.comp shows about each cell.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ViewMode {
+    /// The cell's value in the current palette.
+    Cells,
+    /// How recently each cell changed, fading out exponentially while it stays the same, so that
+    /// moving and oscillating patterns glow and still lifes go dark.
+    Heat,
+    /// How many generations ago each cell last changed.
+    Age,
+    /// The cells which changed this generation.
+    Changes,
+    /// The period each cell has been repeating with over the last few dozen generations.
+    Period,
+}
+
+impl ViewMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Cells => "cells",
+            Self::Heat => "heat",
+            Self::Age => "age",
+            Self::Changes => "changes",
+            Self::Period => "period",
+        }
+    }
+
+    /// Matches the VIEW constants in finalize.comp.
+    pub fn index(self) -> u32 {
+        self as u32
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Cells => Self::Heat,
+            Self::Heat => Self::Age,
+            Self::Age => Self::Changes,
+            Self::Changes => Self::Period,
+            Self::Period => Self::Cells,
+        }
+    }
+}
+
+/// Steadies the view of rules whose empty background flashes between values every generation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Destrobe {
+    Off,
+    /// Cells which match the background of an empty world are shown as empty.
+    Background,
+    /// Each cell is shown as the average of its colors in the last two generations.
+    Average,
+}
+
+impl Destrobe {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Background => "background",
+            Self::Average => "average",
+        }
+    }
+
+    /// Matches the DESTROBE constants in finalize.comp.
+    pub fn index(self) -> u32 {
+        self as u32
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Background,
+            Self::Background => Self::Average,
+            Self::Average => Self::Off,
+        }
+    }
+}
+
+/// Which way a space-time slice runs through the world.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SliceAxis {
+    Row,
+    Column,
+}
+
+impl SliceAxis {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Row => "row",
+            Self::Column => "column",
+        }
+    }
+
+    /// Matches the SLICE constants in simulate.comp and finalize.comp, where zero means there is
+    /// no slice.
+    pub fn index(self) -> u32 {
+        self as u32 + 1
+    }
+}
+
+/// A row or column of the world whose history is shown in the space-time pane.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Slice {
+    pub axis: SliceAxis,
+    /// Which row or column, wrapped into the world.
+    pub index: u32,
+}
+
+/// A rectangle of the image, so that several worlds can be shown side by side.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pane {
+    /// In image pixels.
+    pub left: u32,
+    pub top: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Pane {
+    /// Whether the image position `x`, `y` is inside the pane.
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.left as f32
+            && y >= self.top as f32
+            && x < (self.left + self.width) as f32
+            && y < (self.top + self.height) as f32
+    }
+}
+
 #[derive(Clone)]
 pub struct Options {
     pub kernel_arguments: [i16; PARAMETER_SPACE],
-    pub offset: [i32; 2],
-    pub zoom: u32,
+    /// The world position shown in the top left corner. Not wrapped around the edges of the world.
+    pub offset: [f32; 2],
+    /// Logical pixels per cell, below one when zoomed out.
+    pub zoom: f32,
+    /// Physical pixels per logical pixel, from the window's monitor.
+    pub scale_factor: f32,
+    pub downsampling: Downsampling,
+    pub view_mode: ViewMode,
+    pub destrobe: Destrobe,
+    /// The value of an empty cell in the generation being shown, see `rule::background_value`.
+    pub background: u16,
+    pub palette: Palette,
+    /// Outlined on screen.
+    pub selection: Option<Selection>,
+    /// Whether the HUD is drawn over the world, see hud.rs.
+    pub hud: bool,
+    /// Whether the chart of the world's history is drawn under the HUD, see chart.rs.
+    pub chart: bool,
+    /// Shown over time in a pane along the bottom of the view.
+    pub slice: Option<Slice>,
+    /// Whether a map of the whole world is shown in the corner when the view does not show all
+    /// of it.
+    pub minimap: bool,
+    /// Where the world is drawn, the whole image when this is not set.
+    pub pane: Option<Pane>,
+    /// Where the cells which differ from the world being compared with are drawn, see
+    /// `Renderer::compare_with`.
+    pub difference_pane: Option<Pane>,
+    /// Selects which random soup the world is reset to.
+    pub seed: u32,
+    /// What the world is filled with when it is reset.
+    pub soup: Soup,
     pub rate: u32,
     pub skip: u32,
     pub reset: bool,
@@ -17,12 +217,34 @@ impl Default for Options {
     fn default() -> Self {
         Self {
             kernel_arguments: [0; PARAMETER_SPACE],
-            offset: [0, 0],
-            zoom: 1,
+            offset: [0.0, 0.0],
+            zoom: 1.0,
+            scale_factor: 1.0,
+            downsampling: Downsampling::Average,
+            view_mode: ViewMode::Cells,
+            destrobe: Destrobe::Off,
+            background: 0,
+            palette: Palette::Classic,
+            selection: None,
+            hud: false,
+            chart: false,
+            slice: None,
+            minimap: true,
+            pane: None,
+            difference_pane: None,
+            seed: 0,
+            soup: Soup::default(),
             rate: 1,
             skip: 0,
             reset: true,
             display: true,
         }
     }
-}
\ No newline at end of file
+}
+
+impl Options {
+    /// Physical pixels per cell, which is what the presented image is drawn at.
+    pub fn pixel_zoom(&self) -> f32 {
+        self.zoom * self.scale_factor
+    }
+}
@@ -0,0 +1,222 @@
+//! The colors cells are shown in. The viewer, GIFs, PNGs and Golly exports all get their colors
+//! from here so that they match.
+
+use std::path::Path;
+
+/// How many values a palette has colors for. Larger values are shown as black.
+pub const PALETTE_SIZE: usize = 256;
+
+pub type Color = [u8; 3];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Palette {
+    /// Black, white, then hues which split the color circle more finely for each power-of-two
+    /// band of values.
+    Classic,
+    /// The Okabe-Ito colors, which stay distinct with every common kind of color blindness,
+    /// followed by a viridis ramp.
+    Colorblind,
+    /// Black to white over the values the current rule can produce.
+    Grayscale,
+    /// Loaded from a file, one color per value starting from zero.
+    File { name: String, colors: Vec<Color> },
+}
+
+const OKABE_ITO: [Color; 7] = [
+    [0xE6, 0x9F, 0x00],
+    [0x56, 0xB4, 0xE9],
+    [0x00, 0x9E, 0x73],
+    [0xF0, 0xE4, 0x42],
+    [0x00, 0x72, 0xB2],
+    [0xD5, 0x5E, 0x00],
+    [0xCC, 0x79, 0xA7],
+];
+
+const VIRIDIS: [Color; 5] = [
+    [0x44, 0x01, 0x54],
+    [0x3B, 0x52, 0x8B],
+    [0x21, 0x91, 0x8C],
+    [0x5E, 0xC9, 0x62],
+    [0xFD, 0xE7, 0x25],
+];
+
+fn hue_part(hue: f32) -> f32 {
+    let hue = hue * 3.0;
+    let hue = (hue + 3.0) % 3.0;
+    if hue < 1.0 {
+        hue
+    } else if hue > 2.0 {
+        3.0 - hue
+    } else {
+        1.0
+    }
+}
+
+fn hue(hue: f32) -> Color {
+    [
+        (hue_part(hue + 0.66) * 255.9) as u8,
+        (hue_part(hue + 0.33) * 255.9) as u8,
+        (hue_part(hue) * 255.9) as u8,
+    ]
+}
+
+fn classic_color(value: usize) -> Color {
+    match value {
+        0 => [0; 3],
+        1 => [255; 3],
+        2 => hue(0.0),
+        3 => hue(0.5),
+        _ => {
+            let value = value as u32 - 2;
+            // Each power-of-two band of values splits the hue circle into twice as many parts as
+            // the band before it.
+            let band_size = 1 << (32 - value.leading_zeros() - 1);
+            hue(((value - band_size) as f32 + 0.5) / band_size as f32)
+        }
+    }
+}
+
+fn colorblind_color(value: usize) -> Color {
+    match value {
+        0 => [0; 3],
+        1 => [255; 3],
+        value if value - 2 < OKABE_ITO.len() => OKABE_ITO[value - 2],
+        value => {
+            let first = 2 + OKABE_ITO.len();
+            let amount = (value - first) as f32 / (PALETTE_SIZE - 1 - first) as f32;
+            let position = amount * (VIRIDIS.len() - 1) as f32;
+            let index = (position as usize).min(VIRIDIS.len() - 2);
+            let fraction = position - index as f32;
+            let mut color = [0; 3];
+            for channel in 0..3 {
+                let from = VIRIDIS[index][channel] as f32;
+                let to = VIRIDIS[index + 1][channel] as f32;
+                color[channel] = (from + (to - from) * fraction).round() as u8;
+            }
+            color
+        }
+    }
+}
+
+fn parse_color(line: &str) -> Result<Color, String> {
+    let parts: Vec<_> = line.split_whitespace().collect();
+    let hex = line.trim_start_matches('#');
+    // Checked digit by digit first, since slicing text which is not ASCII can split a character.
+    if parts.len() == 1 && hex.len() == 6 && hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        let channel =
+            |index: usize| u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).unwrap();
+        Ok([channel(0), channel(1), channel(2)])
+    } else if parts.len() == 3 {
+        let channel = |index: usize| {
+            parts[index]
+                .parse()
+                .map_err(|_| format!("\"{}\" is not a color channel", parts[index]))
+        };
+        Ok([channel(0)?, channel(1)?, channel(2)?])
+    } else {
+        Err(format!(
+            "\"{}\" should be a hex color or three numbers from 0 to 255",
+            line
+        ))
+    }
+}
+
+impl Palette {
+    pub const BUILT_IN: [Palette; 3] = [Palette::Classic, Palette::Colorblind, Palette::Grayscale];
+
+    /// Either the name of a built-in palette or a palette file.
+    pub fn from_argument(argument: &str) -> Result<Self, String> {
+        if let Some(palette) = Self::BUILT_IN.iter().find(|p| p.name() == argument) {
+            return Ok(palette.clone());
+        }
+        let path = Path::new(argument);
+        if !path.exists() {
+            return Err(format!(
+                "unknown palette \"{}\", expected classic, colorblind, grayscale or a palette file",
+                argument
+            ));
+        }
+        Self::load(path)
+    }
+
+    /// Palette files have one color per line, either as hex like `#ff8800` or as three numbers
+    /// from 0 to 255. Blank lines and lines starting with `//` are skipped.
+    pub fn load(filename: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(filename)
+            .map_err(|err| format!("Could not read {}: {}", filename.display(), err))?;
+        let colors = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with("//"))
+            .map(parse_color)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("Could not load {}: {}", filename.display(), err))?;
+        if colors.len() < 2 || colors.len() > PALETTE_SIZE {
+            return Err(format!(
+                "Could not load {}: palettes need between 2 and {} colors",
+                filename.display(),
+                PALETTE_SIZE
+            ));
+        }
+        let name = filename
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(Self::File { name, colors })
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Classic => "classic",
+            Self::Colorblind => "colorblind",
+            Self::Grayscale => "grayscale",
+            Self::File { name, .. } => name,
+        }
+    }
+
+    /// The color of every value from 0 to `PALETTE_SIZE - 1`. `largest_value` is the largest value
+    /// the current rule produces, which only the grayscale palette depends on.
+    pub fn colors(&self, largest_value: u16) -> Vec<Color> {
+        (0..PALETTE_SIZE)
+            .map(|value| match self {
+                Self::Classic => classic_color(value),
+                Self::Colorblind => colorblind_color(value),
+                Self::Grayscale => {
+                    let level = (value * 255 / largest_value.max(1) as usize).min(255) as u8;
+                    [level; 3]
+                }
+                Self::File { colors, .. } => colors.get(value).copied().unwrap_or([0; 3]),
+            })
+            .collect()
+    }
+}
+
+/// Looks a cell up in a list of colors from `Palette::colors`.
+pub fn cell_color(colors: &[Color], value: u16) -> Color {
+    colors.get(value as usize).copied().unwrap_or([0; 3])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(parse_color("#ff8000"), Ok([255, 128, 0]));
+        assert_eq!(parse_color("0a0B0c"), Ok([10, 11, 12]));
+    }
+
+    #[test]
+    fn parses_decimal_colors() {
+        assert_eq!(parse_color("255 128 0"), Ok([255, 128, 0]));
+        assert!(parse_color("256 0 0").is_err());
+    }
+
+    #[test]
+    fn rejects_colors_which_are_not_hex() {
+        assert!(parse_color("€€").is_err());
+        assert!(parse_color("#+f+f+f").is_err());
+        assert!(parse_color("#12345").is_err());
+        assert!(parse_color("12 34").is_err());
+    }
+}
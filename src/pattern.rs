@@ -0,0 +1,163 @@
+/// A rectangle of cells which is not part of the world, E.G. one copied out of it or loaded from
+/// a file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pattern {
+    pub width: usize,
+    pub height: usize,
+    /// Row-major, the same layout as the world.
+    pub cells: Vec<u16>,
+}
+
+fn world_index(world_size: u32, x: i32, y: i32) -> usize {
+    let world_size = world_size as i32;
+    let x = x.rem_euclid(world_size);
+    let y = y.rem_euclid(world_size);
+    (y * world_size + x) as usize
+}
+
+impl Pattern {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![0; width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u16 {
+        self.cells[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: u16) {
+        self.cells[y * self.width + x] = value;
+    }
+
+    /// Copies a rectangle out of the world, wrapping around its edges.
+    pub fn copy_from_world(
+        world: &[u16],
+        world_size: u32,
+        left: i32,
+        top: i32,
+        width: usize,
+        height: usize,
+    ) -> Self {
+        let mut pattern = Self::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let index = world_index(world_size, left + x as i32, top + y as i32);
+                pattern.set(x, y, world[index]);
+            }
+        }
+        pattern
+    }
+
+    /// Overwrites a rectangle of the world with this pattern, wrapping around its edges.
+    pub fn paste_into_world(&self, world: &mut [u16], world_size: u32, left: i32, top: i32) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = world_index(world_size, left + x as i32, top + y as i32);
+                world[index] = self.get(x, y);
+            }
+        }
+    }
+
+    pub fn rotated_clockwise(&self) -> Self {
+        let mut rotated = Self::new(self.height, self.width);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                rotated.set(self.height - 1 - y, x, self.get(x, y));
+            }
+        }
+        rotated
+    }
+
+    pub fn rotated_counterclockwise(&self) -> Self {
+        let mut rotated = Self::new(self.height, self.width);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                rotated.set(y, self.width - 1 - x, self.get(x, y));
+            }
+        }
+        rotated
+    }
+
+    /// Mirrors the pattern left to right.
+    pub fn flipped_horizontally(&self) -> Self {
+        let mut flipped = Self::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                flipped.set(self.width - 1 - x, y, self.get(x, y));
+            }
+        }
+        flipped
+    }
+
+    /// Mirrors the pattern top to bottom.
+    pub fn flipped_vertically(&self) -> Self {
+        let mut flipped = Self::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                flipped.set(x, self.height - 1 - y, self.get(x, y));
+            }
+        }
+        flipped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1 2 3
+    /// 4 5 6
+    fn numbered() -> Pattern {
+        Pattern {
+            width: 3,
+            height: 2,
+            cells: vec![1, 2, 3, 4, 5, 6],
+        }
+    }
+
+    #[test]
+    fn rotates_both_ways() {
+        let clockwise = numbered().rotated_clockwise();
+        assert_eq!((clockwise.width, clockwise.height), (2, 3));
+        assert_eq!(clockwise.cells, vec![4, 1, 5, 2, 6, 3]);
+        let counterclockwise = numbered().rotated_counterclockwise();
+        assert_eq!(counterclockwise.cells, vec![3, 6, 2, 5, 1, 4]);
+        assert_eq!(clockwise.rotated_counterclockwise(), numbered());
+        assert_eq!(
+            clockwise.rotated_clockwise(),
+            numbered().flipped_horizontally().flipped_vertically()
+        );
+    }
+
+    #[test]
+    fn flips_both_ways() {
+        assert_eq!(
+            numbered().flipped_horizontally().cells,
+            vec![3, 2, 1, 6, 5, 4]
+        );
+        assert_eq!(
+            numbered().flipped_vertically().cells,
+            vec![4, 5, 6, 1, 2, 3]
+        );
+        assert_eq!(
+            numbered().flipped_horizontally().flipped_horizontally(),
+            numbered()
+        );
+    }
+
+    #[test]
+    fn copies_and_pastes_across_the_edges_of_the_world() {
+        let mut world = vec![0; 64];
+        numbered().paste_into_world(&mut world, 8, 7, -1);
+        // The pattern wraps from the right edge to the left and from the top to the bottom.
+        assert_eq!(&world[56..59], &[2, 3, 0]);
+        assert_eq!(world[63], 1);
+        assert_eq!(&world[0..2], &[5, 6]);
+        assert_eq!(world[7], 4);
+        assert_eq!(world.iter().filter(|&&cell| cell != 0).count(), 6);
+        assert_eq!(Pattern::copy_from_world(&world, 8, -1, 7, 3, 2), numbered());
+    }
+}
@@ -1,7 +1,7 @@
 use vulkano::descriptor::descriptor_set::{DescriptorSet, PersistentDescriptorSet};
 use vulkano::descriptor::pipeline_layout::PipelineLayout;
 use vulkano::device::{Device, Queue};
-use vulkano::format::Format;
+use vulkano::format::{ClearValue, Format};
 use vulkano::image::{ImageDimensions, StorageImage};
 use vulkano::pipeline::ComputePipeline;
 use vulkano::{
@@ -12,8 +12,24 @@ use vulkano::{command_buffer::AutoCommandBufferBuilder, image::view::ImageView};
 
 use std::sync::Arc;
 
-use crate::options::{PARAMETER_SPACE, WORLD_SIZE};
-use crate::{dispatch_manager::DispatchManager, options::Options, shaders};
+use crate::hud::{self, HUD_HEIGHT, HUD_WIDTH};
+use crate::options::PARAMETER_SPACE;
+use crate::palette::PALETTE_SIZE;
+use crate::{
+    dispatch_manager::DispatchManager,
+    options::{Options, Pane, Slice},
+    rule, shaders,
+};
+
+/// How many generations of each cell the period view looks back over. Matches HISTORY_LAYERS in
+/// simulate.comp and finalize.comp.
+const HISTORY_LAYERS: u32 = 32;
+/// How many generations of the space-time slice are kept. Matches SLICE_GENERATIONS in
+/// finalize.comp.
+const SLICE_GENERATIONS: u32 = 1024;
+/// The size of the minimap and its distance from the corner of the view, in logical pixels.
+const MINIMAP_SIZE: f32 = 160.0;
+const MINIMAP_MARGIN: f32 = 8.0;
 
 type RandomizePipeline = ComputePipeline<PipelineLayout<shaders::randomize::MainLayout>>;
 type SimulatePipeline = ComputePipeline<PipelineLayout<shaders::simulate::MainLayout>>;
@@ -23,16 +39,50 @@ type GenericImage = StorageImage<Format>;
 type GenericDescriptorSet = dyn DescriptorSet + Sync + Send;
 
 pub struct Renderer {
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+    world_size: u32,
+    target_image: Arc<GenericImage>,
     target_width: u32,
     target_height: u32,
 
     world_buffer_source: Arc<GenericImage>,
     world_buffer_target: Arc<GenericImage>,
+    cell_history: Arc<GenericImage>,
+    /// The last `HISTORY_LAYERS` generations as a ring, one generation per layer. Only the low
+    /// byte of each cell is kept, which is plenty to tell periods apart and keeps it small.
+    period_history: Arc<GenericImage>,
+    /// The layer the next generation is written to.
+    period_history_next: u32,
+    /// How many layers hold generations of the current world.
+    period_history_length: u32,
+    /// The cells along the slice as a ring, one generation per line.
+    slice_history: Arc<GenericImage>,
+    /// The line the next generation is written to.
+    slice_history_next: u32,
+    /// How many lines hold generations of the current slice.
+    slice_history_length: u32,
+    /// The slice `slice_history` holds.
+    recorded_slice: Option<Slice>,
     cpu_world_buffer: Arc<CpuAccessibleBuffer<[u16]>>,
+    upload_buffer: Arc<CpuAccessibleBuffer<[u16]>>,
+    upload_pending: bool,
+    /// Whether the pending upload replaces the whole world, E.G. a loaded snapshot, rather than
+    /// editing part of it. Histories only start over for new worlds.
+    world_replaced: bool,
+    /// The world the differences pane compares this one with, see `compare_with`.
+    comparison_world: Arc<GenericImage>,
 
     parameter_buffer: Arc<CpuAccessibleBuffer<[i16]>>,
     parameter_image: Arc<GenericImage>,
 
+    palette_buffer: Arc<CpuAccessibleBuffer<[[u8; 4]]>>,
+    palette_image: Arc<GenericImage>,
+
+    hud_buffer: Arc<CpuAccessibleBuffer<[u16]>>,
+    hud_image: Arc<GenericImage>,
+    hud_pending: bool,
+
     randomize_pipeline: Arc<RandomizePipeline>,
     randomize_descriptors: Arc<GenericDescriptorSet>,
 
@@ -43,24 +93,64 @@ pub struct Renderer {
     finalize_descriptors: Arc<GenericDescriptorSet>,
 }
 
+fn target_size(target_image: &GenericImage) -> (u32, u32) {
+    match target_image.dimensions() {
+        ImageDimensions::Dim2d { width, height, .. } => (width, height),
+        _ => panic!("A non-2d image was passed as the target of a Renderer."),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn make_finalize_descriptors(
+    pipeline: &FinalizePipeline,
+    world_buffer_source: &Arc<GenericImage>,
+    target_image: &Arc<GenericImage>,
+    palette_image: &Arc<GenericImage>,
+    cell_history: &Arc<GenericImage>,
+    period_history: &Arc<GenericImage>,
+    hud_image: &Arc<GenericImage>,
+    slice_history: &Arc<GenericImage>,
+    comparison_world: &Arc<GenericImage>,
+) -> Arc<GenericDescriptorSet> {
+    Arc::new(
+        PersistentDescriptorSet::start(pipeline.descriptor_set_layout(0).unwrap().clone())
+            .add_image(ImageView::new(world_buffer_source.clone()).unwrap())
+            .unwrap()
+            .add_image(ImageView::new(target_image.clone()).unwrap())
+            .unwrap()
+            .add_image(ImageView::new(palette_image.clone()).unwrap())
+            .unwrap()
+            .add_image(ImageView::new(cell_history.clone()).unwrap())
+            .unwrap()
+            .add_image(ImageView::new(period_history.clone()).unwrap())
+            .unwrap()
+            .add_image(ImageView::new(hud_image.clone()).unwrap())
+            .unwrap()
+            .add_image(ImageView::new(slice_history.clone()).unwrap())
+            .unwrap()
+            .add_image(ImageView::new(comparison_world.clone()).unwrap())
+            .unwrap()
+            .build()
+            .unwrap(),
+    )
+}
+
 struct RenderBuilder {
     device: Arc<Device>,
     queue: Arc<Queue>,
     target_image: Arc<GenericImage>,
+    world_size: u32,
 }
 
 impl RenderBuilder {
     fn build(self) -> Renderer {
-        let (target_width, target_height) = match self.target_image.dimensions() {
-            ImageDimensions::Dim2d { width, height, .. } => (width, height),
-            _ => panic!("A non-2d image was passed as the target of a Renderer."),
-        };
+        let (target_width, target_height) = target_size(&self.target_image);
 
         let world_buffer_source = StorageImage::new(
             self.device.clone(),
             ImageDimensions::Dim2d {
-                width: WORLD_SIZE,
-                height: WORLD_SIZE,
+                width: self.world_size,
+                height: self.world_size,
                 array_layers: 1,
             },
             Format::R16Uint,
@@ -71,8 +161,45 @@ impl RenderBuilder {
         let world_buffer_target = StorageImage::new(
             self.device.clone(),
             ImageDimensions::Dim2d {
-                width: WORLD_SIZE,
-                height: WORLD_SIZE,
+                width: self.world_size,
+                height: self.world_size,
+                array_layers: 1,
+            },
+            Format::R16Uint,
+            Some(self.queue.family()),
+        )
+        .unwrap();
+
+        // How hot each cell is and how many generations ago it last changed, for the view modes.
+        let cell_history = StorageImage::new(
+            self.device.clone(),
+            ImageDimensions::Dim2d {
+                width: self.world_size,
+                height: self.world_size,
+                array_layers: 1,
+            },
+            Format::R32G32Sfloat,
+            Some(self.queue.family()),
+        )
+        .unwrap();
+
+        let period_history = StorageImage::new(
+            self.device.clone(),
+            ImageDimensions::Dim2d {
+                width: self.world_size,
+                height: self.world_size,
+                array_layers: HISTORY_LAYERS,
+            },
+            Format::R8Uint,
+            Some(self.queue.family()),
+        )
+        .unwrap();
+
+        let slice_history = StorageImage::new(
+            self.device.clone(),
+            ImageDimensions::Dim2d {
+                width: self.world_size,
+                height: SLICE_GENERATIONS,
                 array_layers: 1,
             },
             Format::R16Uint,
@@ -84,7 +211,15 @@ impl RenderBuilder {
             self.device.clone(),
             BufferUsage::all(),
             false,
-            (0..WORLD_SIZE * WORLD_SIZE).map(|_| 0u16),
+            (0..self.world_size * self.world_size).map(|_| 0u16),
+        )
+        .unwrap();
+
+        let upload_buffer = CpuAccessibleBuffer::from_iter(
+            self.device.clone(),
+            BufferUsage::all(),
+            false,
+            (0..self.world_size * self.world_size).map(|_| 0u16),
         )
         .unwrap();
 
@@ -106,6 +241,43 @@ impl RenderBuilder {
         )
         .unwrap();
 
+        let palette_buffer = CpuAccessibleBuffer::from_iter(
+            self.device.clone(),
+            BufferUsage::all(),
+            false,
+            (0..PALETTE_SIZE).map(|_| [0u8; 4]),
+        )
+        .unwrap();
+        let palette_image = StorageImage::new(
+            self.device.clone(),
+            ImageDimensions::Dim1d {
+                width: PALETTE_SIZE as _,
+                array_layers: 1,
+            },
+            Format::R8G8B8A8Unorm,
+            Some(self.queue.family()),
+        )
+        .unwrap();
+
+        let hud_buffer = CpuAccessibleBuffer::from_iter(
+            self.device.clone(),
+            BufferUsage::all(),
+            false,
+            (0..HUD_WIDTH * HUD_HEIGHT).map(|_| hud::HUD_CLEAR),
+        )
+        .unwrap();
+        let hud_image = StorageImage::new(
+            self.device.clone(),
+            ImageDimensions::Dim2d {
+                width: HUD_WIDTH,
+                height: HUD_HEIGHT,
+                array_layers: 1,
+            },
+            Format::R16Uint,
+            Some(self.queue.family()),
+        )
+        .unwrap();
+
         let randomize_shader = shaders::load_randomize_shader(self.device.clone());
         let simulate_shader = shaders::load_simulate_shader(self.device.clone());
         let finalize_shader = shaders::load_finalize_shader(self.device.clone());
@@ -114,7 +286,9 @@ impl RenderBuilder {
             ComputePipeline::new(
                 self.device.clone(),
                 &randomize_shader.main_entry_point(),
-                &(),
+                &shaders::randomize::SpecializationConstants {
+                    WORLD_SIZE: self.world_size,
+                },
                 None,
             )
             .unwrap(),
@@ -133,7 +307,9 @@ impl RenderBuilder {
             ComputePipeline::new(
                 self.device.clone(),
                 &simulate_shader.main_entry_point(),
-                &(),
+                &shaders::simulate::SpecializationConstants {
+                    WORLD_SIZE: self.world_size,
+                },
                 None,
             )
             .unwrap(),
@@ -148,6 +324,12 @@ impl RenderBuilder {
             .unwrap()
             .add_image(ImageView::new(parameter_image.clone()).unwrap())
             .unwrap()
+            .add_image(ImageView::new(cell_history.clone()).unwrap())
+            .unwrap()
+            .add_image(ImageView::new(period_history.clone()).unwrap())
+            .unwrap()
+            .add_image(ImageView::new(slice_history.clone()).unwrap())
+            .unwrap()
             .build()
             .unwrap(),
         );
@@ -156,24 +338,28 @@ impl RenderBuilder {
             ComputePipeline::new(
                 self.device.clone(),
                 &finalize_shader.main_entry_point(),
-                &(),
+                &shaders::finalize::SpecializationConstants {
+                    WORLD_SIZE: self.world_size,
+                },
                 None,
             )
             .unwrap(),
         );
-        let finalize_descriptors: Arc<GenericDescriptorSet> = Arc::new(
-            PersistentDescriptorSet::start(
-                finalize_pipeline.descriptor_set_layout(0).unwrap().clone(),
-            )
-            .add_image(ImageView::new(world_buffer_source.clone()).unwrap())
-            .unwrap()
-            .add_image(ImageView::new(self.target_image.clone()).unwrap())
-            .unwrap()
-            .build()
-            .unwrap(),
+        let finalize_descriptors = make_finalize_descriptors(
+            &finalize_pipeline,
+            &world_buffer_source,
+            &self.target_image,
+            &palette_image,
+            &cell_history,
+            &period_history,
+            &hud_image,
+            &slice_history,
+            &world_buffer_source,
         );
 
         Renderer {
+            world_size: self.world_size,
+            target_image: self.target_image,
             target_width,
             target_height,
 
@@ -183,15 +369,38 @@ impl RenderBuilder {
             parameter_buffer,
             parameter_image,
 
+            palette_buffer,
+            palette_image,
+
+            hud_buffer,
+            hud_image,
+            // Uploaded once so that the image does not start out with garbage in it.
+            hud_pending: true,
+
             world_buffer_source,
             world_buffer_target,
+            cell_history,
+            period_history,
+            period_history_next: 0,
+            period_history_length: 0,
+            slice_history,
+            slice_history_next: 0,
+            slice_history_length: 0,
+            recorded_slice: None,
             cpu_world_buffer,
+            upload_buffer,
+            upload_pending: false,
+            world_replaced: false,
+            comparison_world: world_buffer_source.clone(),
 
             simulate_pipeline,
             simulate_descriptors,
 
             finalize_pipeline,
             finalize_descriptors,
+
+            device: self.device,
+            queue: self.queue,
         }
     }
 }
@@ -201,15 +410,81 @@ impl Renderer {
         device: Arc<Device>,
         queue: Arc<Queue>,
         target_image: Arc<GenericImage>,
+        world_size: u32,
     ) -> Renderer {
         RenderBuilder {
             device,
             queue,
             target_image,
+            world_size,
         }
         .build()
     }
 
+    pub fn world_size(&self) -> u32 {
+        self.world_size
+    }
+
+    /// A renderer with a world of its own which draws into the same image, E.G. to show two
+    /// rules side by side.
+    pub fn new_beside(&self, world_size: u32) -> Renderer {
+        Self::new(
+            self.device.clone(),
+            self.queue.clone(),
+            self.target_image.clone(),
+            world_size,
+        )
+    }
+
+    /// Draws into a new image from now on, E.G. after the window was resized.
+    pub fn set_target_image(&mut self, target_image: Arc<GenericImage>) {
+        let (target_width, target_height) = target_size(&target_image);
+        self.target_width = target_width;
+        self.target_height = target_height;
+        self.target_image = target_image;
+        self.update_finalize_descriptors();
+    }
+
+    /// Makes the differences pane compare this world with the world of `other`, which must be
+    /// the same size.
+    pub fn compare_with(&mut self, other: &Renderer) {
+        self.comparison_world = other.world_buffer_source.clone();
+        self.update_finalize_descriptors();
+    }
+
+    fn update_finalize_descriptors(&mut self) {
+        self.finalize_descriptors = make_finalize_descriptors(
+            &self.finalize_pipeline,
+            &self.world_buffer_source,
+            &self.target_image,
+            &self.palette_image,
+            &self.cell_history,
+            &self.period_history,
+            &self.hud_image,
+            &self.slice_history,
+            &self.comparison_world,
+        );
+    }
+
+    /// Draws several worlds into the same frame, E.G. side by side in their own panes. Whatever
+    /// no pane covers is left black.
+    pub fn render_together(
+        dispatcher: &mut DispatchManager,
+        worlds: Vec<(&mut Renderer, &Options)>,
+    ) -> bool {
+        dispatcher.create_and_submit_commands(|mut builder| {
+            if let Some((renderer, _)) = worlds.first() {
+                builder
+                    .clear_color_image(renderer.target_image.clone(), ClearValue::Float([0.0; 4]))
+                    .unwrap();
+            }
+            for (renderer, options) in worlds {
+                builder = renderer.add_render_commands(builder, options);
+            }
+            builder
+        })
+    }
+
     pub fn render(&mut self, dispatcher: &mut DispatchManager, options: &Options) -> bool {
         if options.display {
             dispatcher
@@ -233,24 +508,83 @@ impl Renderer {
         add_to
             .copy_buffer_to_image(self.parameter_buffer.clone(), self.parameter_image.clone())
             .unwrap();
+        if options.display {
+            {
+                let colors = options
+                    .palette
+                    .colors(rule::largest_value(&options.kernel_arguments));
+                let mut buffer = self.palette_buffer.write().unwrap();
+                for (entry, [r, g, b]) in buffer.iter_mut().zip(colors) {
+                    *entry = [r, g, b, 255];
+                }
+            }
+            add_to
+                .copy_buffer_to_image(self.palette_buffer.clone(), self.palette_image.clone())
+                .unwrap();
+            if self.hud_pending {
+                add_to
+                    .copy_buffer_to_image(self.hud_buffer.clone(), self.hud_image.clone())
+                    .unwrap();
+                self.hud_pending = false;
+            }
+        }
         if options.reset {
+            let soup = &options.soup;
+            let push_data = shaders::randomize::ty::PushData {
+                seed: options.seed,
+                generator: soup.generator.index(),
+                density: soup.density,
+                max_value: soup.values.max_value(options.kernel_arguments[0]),
+                feature_size: soup.feature_size,
+                octaves: soup.octaves,
+            };
             add_to
                 .dispatch(
-                    [WORLD_SIZE / 8, WORLD_SIZE / 8, 1],
+                    [self.world_size / 8, self.world_size / 8, 1],
                     self.randomize_pipeline.clone(),
                     self.randomize_descriptors.clone(),
-                    (),
+                    push_data,
                     vec![],
                 )
                 .unwrap();
         }
+        // A new world has no history.
+        if options.reset || self.world_replaced {
+            add_to
+                .clear_color_image(self.cell_history.clone(), ClearValue::Float([0.0; 4]))
+                .unwrap();
+            self.period_history_length = 0;
+        }
+        if options.reset || self.world_replaced || options.slice != self.recorded_slice {
+            self.slice_history_length = 0;
+            self.recorded_slice = options.slice;
+        }
+        if self.upload_pending {
+            add_to
+                .copy_buffer_to_image(self.upload_buffer.clone(), self.world_buffer_source.clone())
+                .unwrap();
+            self.upload_pending = false;
+            self.world_replaced = false;
+        }
         for _ in 0..options.rate + options.skip {
+            let push_data = shaders::simulate::ty::PushData {
+                history_layer: self.period_history_next,
+                slice_axis: options.slice.map_or(0, |slice| slice.axis.index()),
+                slice_index: options.slice.map_or(0, |slice| slice.index),
+                slice_line: self.slice_history_next,
+            };
+            self.period_history_next = (self.period_history_next + 1) % HISTORY_LAYERS;
+            self.period_history_length = (self.period_history_length + 1).min(HISTORY_LAYERS);
+            if options.slice.is_some() {
+                self.slice_history_next = (self.slice_history_next + 1) % SLICE_GENERATIONS;
+                self.slice_history_length = (self.slice_history_length + 1).min(SLICE_GENERATIONS);
+            }
             add_to
                 .dispatch(
-                    [WORLD_SIZE / 8, WORLD_SIZE / 8, 1],
+                    [self.world_size / 8, self.world_size / 8, 1],
                     self.simulate_pipeline.clone(),
                     self.simulate_descriptors.clone(),
-                    (),
+                    push_data,
                     vec![],
                 )
                 .unwrap()
@@ -263,37 +597,190 @@ impl Renderer {
                     [0, 0, 0],
                     0,
                     0,
-                    [WORLD_SIZE, WORLD_SIZE, 1],
+                    [self.world_size, self.world_size, 1],
                     1,
                 )
                 .unwrap();
         }
+        let (selection_start, selection_size) = match options.selection {
+            Some(selection) => (
+                [
+                    selection.left.rem_euclid(self.world_size as i32),
+                    selection.top.rem_euclid(self.world_size as i32),
+                ],
+                [selection.width as i32, selection.height as i32],
+            ),
+            None => ([0, 0], [0, 0]),
+        };
+        let (minimap_left, minimap_top, minimap_size) =
+            self.minimap_area(options).unwrap_or((0, 0, 0));
+        let pane = self.pane(options);
         let push_data = shaders::finalize::ty::PushData {
-            offset: options.offset,
-            zoom: options.zoom,
+            // Wrapped so that precision is not lost far from the origin.
+            offset: [
+                options.offset[0].rem_euclid(self.world_size as f32),
+                options.offset[1].rem_euclid(self.world_size as f32),
+            ],
+            selection_start,
+            selection_size,
+            zoom: options.pixel_zoom(),
+            downsampling: options.downsampling.index(),
+            view_mode: options.view_mode.index(),
+            history_newest: (self.period_history_next + HISTORY_LAYERS - 1) % HISTORY_LAYERS,
+            history_length: self.period_history_length,
+            destrobe: options.destrobe.index(),
+            background: options.background as u32,
+            hud_scale: if options.hud || options.chart {
+                hud::scale(options.scale_factor)
+            } else {
+                0
+            },
+            slice_axis: options.slice.map_or(0, |slice| slice.axis.index()),
+            slice_index: options.slice.map_or(0, |slice| slice.index),
+            slice_newest: (self.slice_history_next + SLICE_GENERATIONS - 1) % SLICE_GENERATIONS,
+            slice_length: self.slice_history_length,
+            slice_pane_top: self.slice_pane_top(options),
+            minimap_left,
+            minimap_top,
+            minimap_size,
+            pane_left: pane.left,
+            pane_top: pane.top,
+            pane_width: pane.width,
+            pane_height: pane.height,
+            differences: 0,
         };
         if options.display {
-            add_to
-                .dispatch(
-                    [self.target_width / 8, self.target_height / 8, 1],
-                    self.finalize_pipeline.clone(),
-                    self.finalize_descriptors.clone(),
-                    push_data,
-                    vec![],
-                )
-                .unwrap();
+            // Overlays belong to the main pane.
+            let difference_data =
+                options
+                    .difference_pane
+                    .map(|pane| shaders::finalize::ty::PushData {
+                        hud_scale: 0,
+                        slice_axis: 0,
+                        minimap_size: 0,
+                        pane_left: pane.left,
+                        pane_top: pane.top,
+                        pane_width: pane.width,
+                        pane_height: pane.height,
+                        differences: 1,
+                        ..push_data
+                    });
+            for push_data in std::iter::once(push_data).chain(difference_data) {
+                add_to
+                    .dispatch(
+                        [
+                            (push_data.pane_width + 7) / 8,
+                            (push_data.pane_height + 7) / 8,
+                            1,
+                        ],
+                        self.finalize_pipeline.clone(),
+                        self.finalize_descriptors.clone(),
+                        push_data,
+                        vec![],
+                    )
+                    .unwrap();
+            }
         }
+        // The source rather than the target so that uploads show up even when no generations were
+        // simulated.
         add_to
             .copy_image_to_buffer(
-                self.world_buffer_target.clone(),
+                self.world_buffer_source.clone(),
                 self.cpu_world_buffer.clone(),
             )
             .unwrap();
         add_to
     }
 
+    /// Replaces the contents of the world the next time commands are recorded. If the world is
+    /// also being reset that frame, the upload happens after the reset.
+    pub fn upload_world(&mut self, world: &[u16]) {
+        let mut buffer = self.upload_buffer.write().unwrap();
+        buffer.copy_from_slice(world);
+        self.upload_pending = true;
+        self.world_replaced = true;
+    }
+
+    /// Lets `editor` modify the world, which is uploaded the next time commands are recorded.
+    /// Several edits before that are applied on top of each other. The world keeps its history,
+    /// so the effect of a small change can be followed.
+    pub fn edit_world(&mut self, editor: impl FnOnce(&mut [u16])) {
+        let mut buffer = self.upload_buffer.write().unwrap();
+        if !self.upload_pending {
+            buffer.copy_from_slice(&self.cpu_world_buffer.read().unwrap()[..]);
+        }
+        editor(&mut buffer[..]);
+        self.upload_pending = true;
+    }
+
+    /// Replaces what the HUD shows, see `HudImage`.
+    pub fn set_hud(&mut self, pixels: &[u16]) {
+        let mut buffer = self.hud_buffer.write().unwrap();
+        buffer.copy_from_slice(pixels);
+        self.hud_pending = true;
+    }
+
+    /// The first row of the pane covered by the space-time pane when a slice is shown.
+    pub fn slice_pane_top(&self, options: &Options) -> u32 {
+        let height = self.pane(options).height;
+        height - height / 3
+    }
+
+    /// The left, top and size of the minimap in the top right corner of the pane, in image
+    /// pixels, or None when it is hidden.
+    pub fn minimap_area(&self, options: &Options) -> Option<(u32, u32, u32)> {
+        let pane = self.pane(options);
+        let zoom = options.pixel_zoom();
+        let whole_world_visible = pane.width as f32 / zoom >= self.world_size as f32
+            && pane.height as f32 / zoom >= self.world_size as f32;
+        if !options.minimap || whole_world_visible {
+            return None;
+        }
+        let size = (MINIMAP_SIZE * options.scale_factor) as u32;
+        let margin = (MINIMAP_MARGIN * options.scale_factor) as u32;
+        // Hidden in small windows rather than covering most of the view.
+        if size * 2 > pane.width || size * 2 > pane.height {
+            return None;
+        }
+        Some((
+            pane.left + pane.width - size - margin,
+            pane.top + margin,
+            size,
+        ))
+    }
+
+    /// The part of the image the world is drawn in, which is all of it unless it is shared.
+    fn pane(&self, options: &Options) -> Pane {
+        options.pane.unwrap_or(Pane {
+            left: 0,
+            top: 0,
+            width: self.target_width,
+            height: self.target_height,
+        })
+    }
+
+    /// The size of the part of the image the world is drawn in.
+    pub fn view_size(&self, options: &Options) -> (u32, u32) {
+        let pane = self.pane(options);
+        (pane.width, pane.height)
+    }
+
+    pub fn target_size(&self) -> (u32, u32) {
+        (self.target_width, self.target_height)
+    }
+
     pub fn with_cpu_world_buffer<R>(&self, visitor: impl FnOnce(&[u16]) -> R) -> R {
         let slice = self.cpu_world_buffer.read().unwrap();
         visitor(&slice[..])
     }
+
+    /// Visits the world which replaces or edits this one the next time commands are recorded, if
+    /// there is one.
+    pub fn with_pending_upload<R>(&self, visitor: impl FnOnce(&[u16]) -> R) -> Option<R> {
+        if !self.upload_pending {
+            return None;
+        }
+        let slice = self.upload_buffer.read().unwrap();
+        Some(visitor(&slice[..]))
+    }
 }
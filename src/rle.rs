@@ -0,0 +1,291 @@
+//! Golly's extended run length encoding, which supports up to 256 states. State 0 is written as
+//! `.`, states 1 to 24 as `A` to `X`, and higher states put a prefix from `p` to `y` in front of
+//! the letter, each prefix adding another 24.
+
+use crate::pattern::Pattern;
+
+const MAX_LINE_LENGTH: usize = 70;
+const LETTERS_PER_PREFIX: u16 = 24;
+/// The largest state the prefixes can write, `yO`.
+pub const MAX_STATE: u16 = 255;
+const TOO_BIG: &str = "the pattern is bigger than its header says";
+
+pub struct RlePattern {
+    pub pattern: Pattern,
+    /// Whatever was written after `rule =` in the header.
+    pub rule: Option<String>,
+}
+
+fn state_symbol(state: u16) -> String {
+    if state == 0 {
+        return ".".to_string();
+    }
+    let prefix = (state - 1) / LETTERS_PER_PREFIX;
+    let letter = (b'A' + ((state - 1) % LETTERS_PER_PREFIX) as u8) as char;
+    if prefix == 0 {
+        letter.to_string()
+    } else {
+        format!("{}{}", (b'p' + prefix as u8 - 1) as char, letter)
+    }
+}
+
+/// Appends tokens to the body while keeping lines short.
+struct BodyWriter {
+    body: String,
+    line_length: usize,
+}
+
+impl BodyWriter {
+    fn push(&mut self, count: usize, symbol: &str) {
+        let token = if count == 1 {
+            symbol.to_string()
+        } else {
+            format!("{}{}", count, symbol)
+        };
+        if self.line_length + token.len() > MAX_LINE_LENGTH {
+            self.body.push('\n');
+            self.line_length = 0;
+        }
+        self.line_length += token.len();
+        self.body.push_str(&token);
+    }
+}
+
+/// Encodes a pattern. Fails when a cell is above `MAX_STATE`, which cannot be represented.
+pub fn write(pattern: &Pattern, rule: &str, comments: &[String]) -> Result<String, String> {
+    if let Some(&cell) = pattern.cells.iter().find(|&&cell| cell > MAX_STATE) {
+        return Err(format!(
+            "a cell holds {}, RLE can only hold values up to {}",
+            cell, MAX_STATE
+        ));
+    }
+    let mut header = String::new();
+    for comment in comments {
+        header.push_str(&format!("#C {}\n", comment));
+    }
+    header.push_str(&format!(
+        "x = {}, y = {}, rule = {}\n",
+        pattern.width, pattern.height, rule
+    ));
+
+    let mut writer = BodyWriter {
+        body: String::new(),
+        line_length: 0,
+    };
+    let mut pending_rows = 0;
+    for y in 0..pattern.height {
+        let row: Vec<u16> = (0..pattern.width).map(|x| pattern.get(x, y)).collect();
+        // Trailing empty cells are implied by the end of the row.
+        let length = row.len() - row.iter().rev().take_while(|&&cell| cell == 0).count();
+        if length == 0 {
+            pending_rows += 1;
+            continue;
+        }
+        if pending_rows > 0 {
+            writer.push(pending_rows, "$");
+        }
+        let mut x = 0;
+        while x < length {
+            let run = row[x..length]
+                .iter()
+                .take_while(|&&cell| cell == row[x])
+                .count();
+            writer.push(run, &state_symbol(row[x]));
+            x += run;
+        }
+        pending_rows = 1;
+    }
+    writer.push(1, "!");
+    Ok(header + &writer.body + "\n")
+}
+
+fn parse_header(line: &str) -> Result<(usize, usize, Option<String>), String> {
+    let mut width = None;
+    let mut height = None;
+    let mut rule = None;
+    for part in line.split(',') {
+        let mut key_value = part.splitn(2, '=');
+        let key = key_value.next().unwrap().trim();
+        let value = key_value
+            .next()
+            .ok_or_else(|| format!("\"{}\" in the header has no value", key))?
+            .trim();
+        match key {
+            "x" => width = value.parse().ok(),
+            "y" => height = value.parse().ok(),
+            "rule" => rule = Some(value.to_string()),
+            _ => (),
+        }
+    }
+    match (width, height) {
+        (Some(width), Some(height)) => Ok((width, height, rule)),
+        _ => Err("the header needs a numeric x and y".to_string()),
+    }
+}
+
+/// Decodes a pattern no wider or taller than `max_size`, E.G. the size of the world it goes into.
+pub fn parse(text: &str, max_size: usize) -> Result<RlePattern, String> {
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    let header = lines
+        .next()
+        .ok_or_else(|| "the file is empty".to_string())?;
+    let (width, height, rule) = parse_header(header)?;
+    if width > max_size || height > max_size {
+        return Err(format!(
+            "the pattern is {} by {}, larger than the world's {} by {}",
+            width, height, max_size, max_size
+        ));
+    }
+    if width.checked_mul(height).is_none() {
+        return Err("the pattern has too many cells to hold".to_string());
+    }
+    let mut pattern = Pattern::new(width, height);
+
+    let (mut x, mut y) = (0usize, 0usize);
+    let mut count: Option<usize> = None;
+    let mut prefix: Option<u16> = None;
+    'body: for line in lines {
+        for c in line.chars() {
+            let state = match c {
+                '0'..='9' => {
+                    let digit = c.to_digit(10).unwrap() as usize;
+                    count = count
+                        .unwrap_or(0)
+                        .checked_mul(10)
+                        .and_then(|count| count.checked_add(digit));
+                    if count.is_none() {
+                        return Err("a run in the pattern is too long".to_string());
+                    }
+                    continue;
+                }
+                'p'..='y' => {
+                    prefix = Some(c as u16 - 'p' as u16 + 1);
+                    continue;
+                }
+                '!' => break 'body,
+                '$' => {
+                    // Ending the last row is fine, going past it is not.
+                    y = match y.checked_add(count.take().unwrap_or(1)) {
+                        Some(next) if next <= height => next,
+                        _ => return Err(TOO_BIG.to_string()),
+                    };
+                    x = 0;
+                    continue;
+                }
+                'b' | '.' => 0,
+                'o' => 1,
+                'A'..='X' => {
+                    let state = prefix.take().unwrap_or(0) * LETTERS_PER_PREFIX
+                        + (c as u16 - 'A' as u16)
+                        + 1;
+                    if state > MAX_STATE {
+                        return Err(format!("the state {} is above {}", state, MAX_STATE));
+                    }
+                    state
+                }
+                c if c.is_whitespace() => continue,
+                c => return Err(format!("unexpected character '{}' in the pattern", c)),
+            };
+            let run = count.take().unwrap_or(1);
+            // Never overflows, since x only ever gets as far as the width.
+            if y >= height || run > width - x {
+                return Err(TOO_BIG.to_string());
+            }
+            for _ in 0..run {
+                pattern.set(x, y, state);
+                x += 1;
+            }
+        }
+    }
+    Ok(RlePattern { pattern, rule })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(rows: &[&[u16]]) -> Pattern {
+        let mut pattern = Pattern::new(rows[0].len(), rows.len());
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &value) in row.iter().enumerate() {
+                pattern.set(x, y, value);
+            }
+        }
+        pattern
+    }
+
+    fn round_trip(original: &Pattern) -> Pattern {
+        let text = write(original, "4-0-0-0-1", &[]).unwrap();
+        let parsed = parse(&text, 8).unwrap();
+        assert_eq!(parsed.rule.as_deref(), Some("4-0-0-0-1"));
+        parsed.pattern
+    }
+
+    #[test]
+    fn state_symbols() {
+        assert_eq!(state_symbol(0), ".");
+        assert_eq!(state_symbol(1), "A");
+        assert_eq!(state_symbol(24), "X");
+        assert_eq!(state_symbol(25), "pA");
+        assert_eq!(state_symbol(255), "yO");
+    }
+
+    #[test]
+    fn round_trips_multi_state_cells() {
+        let original = pattern(&[&[0, 1, 1, 25], &[255, 0, 0, 0], &[24, 48, 49, 200]]);
+        let parsed = round_trip(&original);
+        assert_eq!(parsed.cells, original.cells);
+        assert!(write(&original, "", &[]).unwrap().contains("yO"));
+    }
+
+    #[test]
+    fn round_trips_empty_rows() {
+        let original = pattern(&[&[0, 0, 0], &[3, 0, 0], &[0, 0, 0], &[0, 0, 2], &[0, 0, 0]]);
+        let parsed = round_trip(&original);
+        assert_eq!((parsed.width, parsed.height), (3, 5));
+        assert_eq!(parsed.cells, original.cells);
+    }
+
+    #[test]
+    fn parses_two_state_patterns() {
+        let parsed = parse("#C a glider\nx = 3, y = 3\nbob$2bo$3o!", 8).unwrap();
+        assert_eq!(parsed.rule, None);
+        assert_eq!(parsed.pattern.cells, vec![0, 1, 0, 0, 0, 1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn rejects_runs_which_overflow() {
+        assert!(parse("x = 3, y = 3\n99999999999999999999999o!", 8).is_err());
+        assert!(parse("x = 3, y = 3\n99999999999999999999999$o!", 8).is_err());
+    }
+
+    #[test]
+    fn rejects_runs_past_the_header_size() {
+        assert!(parse("x = 3, y = 3\n4o!", 8).is_err());
+        assert!(parse("x = 3, y = 3\no4$o!", 8).is_err());
+        assert!(parse("x = 3, y = 3\no$o$o$o!", 8).is_err());
+        assert!(parse("x = 3, y = 3\no$o$o$!", 8).is_ok());
+    }
+
+    #[test]
+    fn rejects_patterns_larger_than_the_world() {
+        assert!(parse("x = 8, y = 8\no!", 8).is_ok());
+        assert!(parse("x = 9, y = 1\no!", 8).is_err());
+        assert!(parse("x = 1, y = 9\no!", 8).is_err());
+        let huge = format!("x = {}, y = {}\no!", usize::MAX, usize::MAX);
+        assert!(parse(&huge, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn rejects_states_above_the_largest() {
+        assert_eq!(
+            parse("x = 1, y = 1\nyO!", 8).unwrap().pattern.cells,
+            vec![255]
+        );
+        assert!(parse("x = 1, y = 1\nyP!", 8).is_err());
+        assert!(parse("x = 1, y = 1\nyX!", 8).is_err());
+    }
+}
@@ -0,0 +1,193 @@
+use crate::options::PARAMETER_SPACE;
+
+pub type KernelArguments = [i16; PARAMETER_SPACE];
+
+/// Parses a rule written either in Square Sum Map notation ("4 % 0 0 0 1") or in the dashed form
+/// used in capture filenames ("4-0-0-0-1"). Coefficients which are left out are zero.
+pub fn parse(text: &str) -> Result<KernelArguments, String> {
+    let mut arguments = [0i16; PARAMETER_SPACE];
+    let parts = text
+        .split(|c: char| c.is_whitespace() || c == '%' || c == '-' || c == ',')
+        .filter(|part| !part.is_empty());
+    let mut count = 0;
+    for part in parts {
+        if count >= PARAMETER_SPACE {
+            return Err(format!("the rule \"{}\" has too many coefficients", text));
+        }
+        arguments[count] = part
+            .parse()
+            .map_err(|_| format!("\"{}\" in the rule \"{}\" is not a number", part, text))?;
+        count += 1;
+    }
+    if count == 0 {
+        return Err(format!("\"{}\" is not a rule", text));
+    }
+    let divisor = arguments[0];
+    if divisor < 1 || divisor as usize >= PARAMETER_SPACE {
+        return Err(format!(
+            "the divisor of a rule must be between 1 and {}",
+            PARAMETER_SPACE - 1
+        ));
+    }
+    if count > divisor as usize + 1 {
+        return Err(format!(
+            "the rule \"{}\" has more than {} coefficients",
+            text, divisor
+        ));
+    }
+    if arguments.iter().any(|&argument| argument < 0) {
+        return Err(format!("the rule \"{}\" has negative coefficients", text));
+    }
+    Ok(arguments)
+}
+
+pub fn divisor(arguments: &KernelArguments) -> usize {
+    arguments[0] as usize
+}
+
+pub fn coefficients(arguments: &KernelArguments) -> &[i16] {
+    &arguments[1..1 + divisor(arguments)]
+}
+
+/// Formats a rule in Square Sum Map notation, E.G. "4 % 0 0 0 1".
+pub fn notation(arguments: &KernelArguments) -> String {
+    let coefficients = coefficients(arguments)
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!("{} % {}", arguments[0], coefficients)
+}
+
+/// Formats a rule the way it appears in capture filenames, E.G. "4-0-0-0-1". Trailing zeros are
+/// left out.
+pub fn filename_part(arguments: &KernelArguments) -> String {
+    let last_nonzero = arguments.len() - arguments.iter().rev().position(|a| *a != 0).unwrap();
+    arguments[..last_nonzero]
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// The largest value a cell will usually hold: either a value the rule can produce or a value
+/// below the divisor.
+pub fn largest_value(arguments: &KernelArguments) -> u16 {
+    let largest_coefficient = coefficients(arguments).iter().copied().max().unwrap_or(0);
+    largest_coefficient.max(arguments[0] - 1).max(1) as u16
+}
+
+/// The value a cell takes when it and its eight neighbors add up to `neighborhood`.
+pub fn next_value(arguments: &KernelArguments, neighborhood: u32) -> u16 {
+    coefficients(arguments)[neighborhood as usize % divisor(arguments)] as u16
+}
+
+/// Every rule which differs from `arguments` by one in a single coefficient, wrapping around
+/// the divisor, in the order of the coefficients with the increase first.
+pub fn neighbors(arguments: &KernelArguments) -> Vec<KernelArguments> {
+    let divisor = arguments[0];
+    let mut neighbors = Vec::new();
+    for index in 1..=divisor as usize {
+        for step in &[1, -1] {
+            let mut neighbor = *arguments;
+            neighbor[index] = (neighbor[index] + step).rem_euclid(divisor);
+            if neighbor != *arguments && !neighbors.contains(&neighbor) {
+                neighbors.push(neighbor);
+            }
+        }
+    }
+    neighbors
+}
+
+/// The value every cell of an empty world has after `generation` generations. Rules with a
+/// nonzero first coefficient make the empty background flash between values.
+pub fn background_value(arguments: &KernelArguments, generation: u64) -> u16 {
+    // Each value only depends on the one before, so the sequence starts repeating once a value
+    // comes up again.
+    let mut seen = std::collections::HashMap::new();
+    let mut sequence = Vec::new();
+    let mut value = 0u16;
+    loop {
+        if sequence.len() as u64 == generation {
+            return value;
+        }
+        if let Some(&start) = seen.get(&value) {
+            let cycle = (sequence.len() - start) as u64;
+            return sequence[start + ((generation - start as u64) % cycle) as usize];
+        }
+        seen.insert(value, sequence.len());
+        sequence.push(value);
+        value = next_value(arguments, value as u32 * 9);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_both_notations() {
+        let expected = {
+            let mut arguments = [0; PARAMETER_SPACE];
+            arguments[..5].copy_from_slice(&[4, 0, 0, 0, 1]);
+            arguments
+        };
+        assert_eq!(parse("4 % 0 0 0 1"), Ok(expected));
+        assert_eq!(parse("4-0-0-0-1"), Ok(expected));
+        // Coefficients which are left out are zero.
+        assert_eq!(parse("4 % 0 0"), parse("4-0-0-0-0"));
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        assert!(parse("").is_err());
+        assert!(parse("0 % 1").is_err());
+        assert!(parse("4 % 0 0 0 0 1").is_err());
+        // A minus sign separates coefficients, so it cannot make one negative.
+        assert_eq!(parse("4 % 0 -1"), parse("4 % 0 1"));
+        assert!(parse("4 % a").is_err());
+        assert!(parse(&format!("{}", PARAMETER_SPACE)).is_err());
+    }
+
+    #[test]
+    fn formats_rules() {
+        let arguments = parse("4 % 0 3 0 0").unwrap();
+        assert_eq!(notation(&arguments), "4 % 0 3 0 0");
+        assert_eq!(filename_part(&arguments), "4-0-3");
+        assert_eq!(parse(&filename_part(&arguments)), Ok(arguments));
+    }
+
+    #[test]
+    fn neighbors_wrap_around_the_divisor() {
+        let notations: Vec<_> = neighbors(&parse("2 % 1 0").unwrap())
+            .iter()
+            .map(notation)
+            .collect();
+        assert_eq!(notations, vec!["2 % 0 0", "2 % 1 1"]);
+        assert!(neighbors(&parse("1 % 0").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn next_values_wrap_the_neighborhood_sum() {
+        let arguments = parse("3 % 0 2 1").unwrap();
+        assert_eq!(next_value(&arguments, 0), 0);
+        assert_eq!(next_value(&arguments, 4), 2);
+        assert_eq!(next_value(&arguments, 8), 1);
+    }
+
+    #[test]
+    fn background_values_follow_the_empty_world() {
+        // Nine 0s sum to 0, nine 1s to 1 modulo 4 and nine 3s to 3, so the background cycles
+        // through 0, 1 and 3.
+        let arguments = parse("4 % 1 3 0 0").unwrap();
+        assert_eq!(background_value(&arguments, 0), 0);
+        assert_eq!(background_value(&arguments, 1), 1);
+        assert_eq!(background_value(&arguments, 2), 3);
+        assert_eq!(background_value(&arguments, 3), 0);
+        assert_eq!(background_value(&arguments, 1_000_000), 1);
+        assert_eq!(
+            background_value(&parse("4 % 0 0 0 1").unwrap(), 1_000_000),
+            0
+        );
+    }
+}
@@ -0,0 +1,57 @@
+/// A rectangle of the world. The corner is not wrapped, so a selection which crosses the edge of
+/// the world continues on the other side.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Selection {
+    pub left: i32,
+    pub top: i32,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Selection {
+    /// The smallest selection containing both cells.
+    pub fn between(a: (i32, i32), b: (i32, i32)) -> Self {
+        Self {
+            left: a.0.min(b.0),
+            top: a.1.min(b.1),
+            width: (a.0 - b.0).unsigned_abs() as usize + 1,
+            height: (a.1 - b.1).unsigned_abs() as usize + 1,
+        }
+    }
+
+    /// A selection of the given size with the same center as this one.
+    pub fn resized_around_center(&self, width: usize, height: usize) -> Self {
+        Self {
+            left: self.left + (self.width as i32 - width as i32) / 2,
+            top: self.top + (self.height as i32 - height as i32) / 2,
+            width,
+            height,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn between_contains_both_corners() {
+        let expected = Selection {
+            left: -2,
+            top: 1,
+            width: 5,
+            height: 4,
+        };
+        assert_eq!(Selection::between((-2, 4), (2, 1)), expected);
+        assert_eq!(Selection::between((2, 1), (-2, 4)), expected);
+        assert_eq!(Selection::between((3, 3), (3, 3)).width, 1);
+    }
+
+    #[test]
+    fn resizing_keeps_the_center() {
+        let selection = Selection::between((0, 0), (5, 3));
+        let rotated = selection.resized_around_center(4, 6);
+        assert_eq!((rotated.left, rotated.top), (1, -1));
+        assert_eq!(rotated.resized_around_center(6, 4), selection);
+    }
+}
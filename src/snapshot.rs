@@ -0,0 +1,185 @@
+use std::{
+    convert::TryInto,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    options::{self, PARAMETER_SPACE},
+    rule::{self, KernelArguments},
+};
+
+const MAGIC: &[u8; 8] = b"MATRIX3W";
+const VERSION: u32 = 1;
+
+/// Everything needed to bring a world back exactly as it was.
+///
+/// On disk this is the magic bytes and a version number, then little endian fields in the order
+/// they are declared here. The rule is stored as its divisor followed by that many coefficients,
+/// and the cells are zlib compressed little endian u16s in row-major order.
+pub struct WorldSnapshot {
+    pub kernel_arguments: KernelArguments,
+    pub generation: u64,
+    pub seed: u32,
+    pub world_size: u32,
+    pub cells: Vec<u16>,
+}
+
+/// Reads little endian fields off the front of a byte slice.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if self.data.len() < count {
+            return Err("the snapshot is truncated".to_string());
+        }
+        let (taken, rest) = self.data.split_at(count);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+impl WorldSnapshot {
+    pub fn save(&self, filename: &Path) -> std::io::Result<()> {
+        std::fs::write(filename, self.to_bytes())
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&VERSION.to_le_bytes());
+        data.extend_from_slice(&(rule::divisor(&self.kernel_arguments) as u16).to_le_bytes());
+        for &coefficient in rule::coefficients(&self.kernel_arguments) {
+            data.extend_from_slice(&(coefficient as u16).to_le_bytes());
+        }
+        data.extend_from_slice(&self.generation.to_le_bytes());
+        data.extend_from_slice(&self.seed.to_le_bytes());
+        data.extend_from_slice(&self.world_size.to_le_bytes());
+        let mut cells = Vec::with_capacity(self.cells.len() * 2);
+        for &cell in &self.cells {
+            cells.extend_from_slice(&cell.to_le_bytes());
+        }
+        data.append(&mut deflate::deflate_bytes_zlib(&cells[..]));
+        data
+    }
+
+    pub fn load(filename: &Path) -> Result<Self, String> {
+        let data = std::fs::read(filename)
+            .map_err(|err| format!("Could not read {}: {}", filename.display(), err))?;
+        Self::parse(&data[..])
+            .map_err(|err| format!("Could not load {}: {}", filename.display(), err))
+    }
+
+    fn parse(data: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader { data };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err("it is not a world snapshot".to_string());
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(format!("unsupported snapshot version {}", version));
+        }
+        let mut kernel_arguments = [0; PARAMETER_SPACE];
+        let divisor = reader.u16()? as usize;
+        if divisor == 0 || divisor >= PARAMETER_SPACE {
+            return Err(format!("invalid divisor {}", divisor));
+        }
+        kernel_arguments[0] = divisor as i16;
+        for coefficient in &mut kernel_arguments[1..=divisor] {
+            *coefficient = reader.u16()? as i16;
+        }
+        let generation = reader.u64()?;
+        let seed = reader.u32()?;
+        let world_size = reader.u32()?;
+        options::check_world_size(world_size)?;
+        let cells = inflate::inflate_bytes_zlib(reader.data)?;
+        let expected_length = (world_size as usize)
+            .checked_mul(world_size as usize * 2)
+            .ok_or("the world size is too large")?;
+        if cells.len() != expected_length {
+            return Err("the cell data does not match the world size".to_string());
+        }
+        let cells = cells
+            .chunks_exact(2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+            .collect();
+        Ok(Self {
+            kernel_arguments,
+            generation,
+            seed,
+            world_size,
+            cells,
+        })
+    }
+
+    /// A filename for a new snapshot in `directory` which says what it contains.
+    pub fn default_filename(&self, directory: &Path) -> PathBuf {
+        directory.join(format!(
+            "WORLD PARAMS {} SEED {} GEN {}.m3w",
+            rule::filename_part(&self.kernel_arguments),
+            self.seed,
+            self.generation
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(world_size: u32) -> WorldSnapshot {
+        WorldSnapshot {
+            kernel_arguments: rule::parse("4 % 0 3 0 1").unwrap(),
+            generation: 1234,
+            seed: 99,
+            world_size,
+            cells: (0..world_size * world_size)
+                .map(|i| (i % 7) as u16)
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let original = snapshot(16);
+        let parsed = WorldSnapshot::parse(&original.to_bytes()).unwrap();
+        assert_eq!(parsed.kernel_arguments, original.kernel_arguments);
+        assert_eq!(parsed.generation, 1234);
+        assert_eq!(parsed.seed, 99);
+        assert_eq!(parsed.world_size, 16);
+        assert_eq!(parsed.cells, original.cells);
+    }
+
+    #[test]
+    fn rejects_invalid_world_sizes() {
+        assert!(WorldSnapshot::parse(&snapshot(0).to_bytes()).is_err());
+        assert!(WorldSnapshot::parse(&snapshot(12).to_bytes()).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_and_foreign_data() {
+        let data = snapshot(8).to_bytes();
+        assert!(WorldSnapshot::parse(&data[..20]).is_err());
+        assert!(WorldSnapshot::parse(b"GIF89a and so on").is_err());
+    }
+
+    #[test]
+    fn rejects_cells_which_do_not_match_the_size() {
+        let mut wrong = snapshot(8);
+        wrong.cells.truncate(60);
+        assert!(WorldSnapshot::parse(&wrong.to_bytes()).is_err());
+    }
+}
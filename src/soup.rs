@@ -0,0 +1,130 @@
+use std::str::FromStr;
+
+/// Which pattern `randomize.comp` fills the world with when it is reset.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Generator {
+    /// Random cells masked by value noise, producing patchy clouds of soup.
+    Noise,
+    /// Every cell is independently random.
+    Uniform,
+    /// A single nonzero cell in the middle of the world.
+    SingleCell,
+    /// Uniform soup inside a disc in the middle of the world.
+    Blob,
+    /// Uniform soup mirrored across both axes.
+    MirrorSoup,
+    /// Uniform soup with four-fold rotational symmetry.
+    RotationalSoup,
+    /// Horizontal bands of uniform soup separated by empty bands.
+    Stripes,
+}
+
+impl Generator {
+    const ALL: [Generator; 7] = [
+        Self::Noise,
+        Self::Uniform,
+        Self::SingleCell,
+        Self::Blob,
+        Self::MirrorSoup,
+        Self::RotationalSoup,
+        Self::Stripes,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Noise => "noise",
+            Self::Uniform => "uniform",
+            Self::SingleCell => "cell",
+            Self::Blob => "blob",
+            Self::MirrorSoup => "mirror",
+            Self::RotationalSoup => "rotational",
+            Self::Stripes => "stripes",
+        }
+    }
+
+    /// The index randomize.comp uses to select this generator.
+    pub fn index(self) -> u32 {
+        Self::ALL.iter().position(|&g| g == self).unwrap() as u32
+    }
+
+    pub fn next(self) -> Self {
+        Self::ALL[(self.index() as usize + 1) % Self::ALL.len()]
+    }
+}
+
+impl FromStr for Generator {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|g| g.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::ALL.iter().map(|g| g.name()).collect();
+                format!(
+                    "unknown generator \"{}\", expected one of {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// The largest value a random cell can take.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValueRange {
+    Fixed(u32),
+    /// Every value below the divisor of the current rule.
+    BelowDivisor,
+}
+
+impl ValueRange {
+    pub fn max_value(self, divisor: i16) -> u32 {
+        match self {
+            Self::Fixed(max) => max.max(1),
+            Self::BelowDivisor => (divisor as u32).max(2) - 1,
+        }
+    }
+}
+
+impl FromStr for ValueRange {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        if text == "divisor" {
+            Ok(Self::BelowDivisor)
+        } else {
+            text.parse()
+                .map(Self::Fixed)
+                .map_err(|_| format!("\"{}\" is not a number or \"divisor\"", text))
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Soup {
+    pub generator: Generator,
+    /// Fraction of cells inside the soup which start out nonzero. Not used by the single cell
+    /// generator, nor by the noise generator, whose mask decides where cells start out and which
+    /// leaves a cell zero as often as any other value.
+    pub density: f32,
+    /// Nonzero cells get a random value from 1 up to and including this.
+    pub values: ValueRange,
+    /// Radius of blobs and width of stripes, in cells.
+    pub feature_size: u32,
+    /// How many layers of noise make up the noise mask.
+    pub octaves: u32,
+}
+
+impl Default for Soup {
+    fn default() -> Self {
+        Self {
+            generator: Generator::Noise,
+            density: 0.5,
+            values: ValueRange::Fixed(9),
+            feature_size: 64,
+            octaves: 2,
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! A library of named patterns. Each rule gets its own directory of RLE files, since a pattern
+//! saved under one rule rarely behaves the same way under another.
+
+use std::path::{Path, PathBuf};
+
+use crate::{
+    pattern::Pattern,
+    rle,
+    rule::{self, KernelArguments},
+};
+
+pub struct Stamp {
+    pub name: String,
+    pub pattern: Pattern,
+}
+
+fn rule_directory(library: &Path, arguments: &KernelArguments) -> PathBuf {
+    library.join(rule::filename_part(arguments))
+}
+
+/// Names become filenames, so anything which could be awkward in a path is dropped.
+pub fn is_name_character(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == ' ' || c == '-' || c == '_'
+}
+
+pub fn save(
+    library: &Path,
+    arguments: &KernelArguments,
+    name: &str,
+    pattern: &Pattern,
+) -> std::io::Result<PathBuf> {
+    let directory = rule_directory(library, arguments);
+    std::fs::create_dir_all(&directory)?;
+    let filename = directory.join(format!("{}.rle", name));
+    let comments = [format!("Stamp {}", name)];
+    let text = rle::write(pattern, &rule::notation(arguments), &comments)
+        .map_err(|message| std::io::Error::new(std::io::ErrorKind::InvalidData, message))?;
+    std::fs::write(&filename, text)?;
+    Ok(filename)
+}
+
+/// Every stamp saved for the rule, sorted by name. Files which do not parse, are larger than the
+/// world or whose header names a different rule are skipped.
+pub fn list(library: &Path, arguments: &KernelArguments, world_size: u32) -> Vec<Stamp> {
+    let entries = match std::fs::read_dir(rule_directory(library, arguments)) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut stamps: Vec<_> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("rle") {
+                return None;
+            }
+            let name = path.file_stem()?.to_str()?.to_string();
+            let parsed = std::fs::read_to_string(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|text| rle::parse(&text, world_size as usize));
+            match parsed {
+                Ok(parsed) => {
+                    let same_rule = match &parsed.rule {
+                        Some(text) => rule::parse(text) == Ok(*arguments),
+                        None => true,
+                    };
+                    if same_rule {
+                        Some(Stamp {
+                            name,
+                            pattern: parsed.pattern,
+                        })
+                    } else {
+                        None
+                    }
+                }
+                Err(message) => {
+                    eprintln!("Skipping stamp {}: {}", path.display(), message);
+                    None
+                }
+            }
+        })
+        .collect();
+    stamps.sort_by(|a, b| a.name.cmp(&b.name));
+    stamps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh library in the temporary directory, removed when it is dropped.
+    struct Library(PathBuf);
+
+    impl Library {
+        fn new(test: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("stamps-test-{}-{}", test, std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            Self(path)
+        }
+    }
+
+    impl Drop for Library {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn pattern(cells: &[u16]) -> Pattern {
+        Pattern {
+            width: cells.len(),
+            height: 1,
+            cells: cells.to_vec(),
+        }
+    }
+
+    #[test]
+    fn names_are_safe_in_paths() {
+        assert!("glider_2-b C".chars().all(is_name_character));
+        assert!(!"../glider".chars().all(is_name_character));
+        assert!(!"a/b".chars().all(is_name_character));
+    }
+
+    #[test]
+    fn lists_saved_stamps_by_name() {
+        let library = Library::new("list");
+        let arguments = rule::parse("4 % 0 0 0 1").unwrap();
+        assert!(list(&library.0, &arguments, 8).is_empty());
+        let filename = save(&library.0, &arguments, "b", &pattern(&[1, 0, 3])).unwrap();
+        assert!(filename.starts_with(library.0.join("4-0-0-0-1")));
+        save(&library.0, &arguments, "a", &pattern(&[2])).unwrap();
+        // Files which are not RLE or do not parse are left out.
+        std::fs::write(filename.with_file_name("notes.txt"), "not a stamp").unwrap();
+        std::fs::write(filename.with_file_name("broken.rle"), "x = 1").unwrap();
+
+        let stamps = list(&library.0, &arguments, 8);
+        let names: Vec<_> = stamps.iter().map(|stamp| stamp.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b"]);
+        assert_eq!(stamps[1].pattern.cells, vec![1, 0, 3]);
+    }
+
+    #[test]
+    fn stamps_belong_to_their_rule() {
+        let library = Library::new("rule");
+        let arguments = rule::parse("4 % 0 0 0 1").unwrap();
+        save(&library.0, &arguments, "glider", &pattern(&[1])).unwrap();
+        assert!(list(&library.0, &rule::parse("3 % 0 1").unwrap(), 8).is_empty());
+
+        // A stamp which was copied into the wrong rule's directory is skipped.
+        let directory = library.0.join("4-0-0-0-1");
+        let text = rle::write(&pattern(&[1]), "3 % 0 1", &[]).unwrap();
+        std::fs::write(directory.join("copied.rle"), text).unwrap();
+        let names: Vec<_> = list(&library.0, &arguments, 8)
+            .into_iter()
+            .map(|stamp| stamp.name)
+            .collect();
+        assert_eq!(names, vec!["glider"]);
+    }
+
+    #[test]
+    fn refuses_cells_which_rle_cannot_hold() {
+        let library = Library::new("large");
+        let arguments = rule::parse("4 % 0 0 0 1").unwrap();
+        let error = save(&library.0, &arguments, "big", &pattern(&[300])).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
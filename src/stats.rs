@@ -1,8 +1,8 @@
-use std::fs::File;
+use std::{fs::File, path::Path};
 
 use gif::{Encoder, Frame, Repeat};
 
-use crate::{options::WORLD_SIZE, renderer::Renderer};
+use crate::{palette::Color, renderer::Renderer};
 
 struct StatCruncher<'a> {
     world: &'a [u16],
@@ -12,15 +12,30 @@ impl<'a> StatCruncher<'a> {
     fn crunch(self) -> Stats {
         let world = self.world;
         let total_cells = world.len();
-        let occupied_cells = world.iter().filter(|&&cell| cell != 0).count();
+        let mut value_counts = vec![0];
+        for &cell in world {
+            let cell = cell as usize;
+            if cell >= value_counts.len() {
+                value_counts.resize(cell + 1, 0);
+            }
+            value_counts[cell] += 1;
+        }
+        let occupied_cells = total_cells - value_counts[0] as usize;
         let population_density = occupied_cells as f32 / total_cells as f32;
-        Stats { population_density }
+        Stats {
+            population_density,
+            total_cells: total_cells as u32,
+            value_counts,
+        }
     }
 }
 
 #[derive(Debug)]
 pub struct Stats {
     pub population_density: f32,
+    pub total_cells: u32,
+    /// How many cells hold each value, up to the largest value in the world.
+    pub value_counts: Vec<u32>,
 }
 
 impl Stats {
@@ -29,6 +44,87 @@ impl Stats {
     }
 }
 
+/// Older samples are thinned out once a history holds this many.
+const MAX_HISTORY_SAMPLES: usize = 2048;
+
+pub struct HistorySample {
+    pub generation: u64,
+    pub stats: Stats,
+}
+
+/// Stats of the world over time, for the chart and CSV export. Long runs keep a sample every
+/// few generations so that the whole run fits.
+pub struct History {
+    samples: Vec<HistorySample>,
+    /// How many generations apart samples are taken.
+    spacing: u64,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            samples: Vec::new(),
+            spacing: 1,
+        }
+    }
+
+    pub fn samples(&self) -> &[HistorySample] {
+        &self.samples
+    }
+
+    /// Adds a sample unless one was taken too recently, or less than `min_spacing` generations
+    /// ago. `stats` is only called when it is needed. Going back to an earlier generation, E.G.
+    /// after a reset, starts a new history.
+    pub fn record(&mut self, generation: u64, min_spacing: u64, stats: impl FnOnce() -> Stats) {
+        if let Some(last) = self.samples.last() {
+            if generation < last.generation {
+                *self = Self::new();
+            } else if generation - last.generation < self.spacing.max(min_spacing) {
+                return;
+            }
+        }
+        self.samples.push(HistorySample {
+            generation,
+            stats: stats(),
+        });
+        if self.samples.len() > MAX_HISTORY_SAMPLES {
+            let mut index = 0;
+            self.samples.retain(|_| {
+                index += 1;
+                index % 2 == 1
+            });
+            self.spacing *= 2;
+        }
+    }
+
+    /// One row per sample with the generation, population density and the count of every value.
+    pub fn to_csv(&self) -> String {
+        let values = self
+            .samples
+            .iter()
+            .map(|sample| sample.stats.value_counts.len())
+            .max()
+            .unwrap_or(0);
+        let mut csv = String::from("generation,population_density");
+        for value in 0..values {
+            csv.push_str(&format!(",value_{}", value));
+        }
+        csv.push('\n');
+        for sample in &self.samples {
+            csv.push_str(&format!(
+                "{},{}",
+                sample.generation, sample.stats.population_density
+            ));
+            for value in 0..values {
+                let count = sample.stats.value_counts.get(value).copied().unwrap_or(0);
+                csv.push_str(&format!(",{}", count));
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
 pub struct Judge {
     snapshots: Vec<Stats>,
 }
@@ -93,21 +189,25 @@ impl AutomaticJudgement {
 
 struct Snapshot {
     data: Vec<u16>,
+    world_size: u32,
 }
 
 const CLIP_SIZE: i32 = 20;
 
 impl Snapshot {
     fn of(world: &Renderer) -> Self {
+        let world_size = world.world_size();
         world.with_cpu_world_buffer(|world| Self {
             data: Vec::from(world),
+            world_size,
         })
     }
 
     fn pixel(&self, x: i32, y: i32) -> u16 {
-        let x = (x + WORLD_SIZE as i32) as u32 % WORLD_SIZE;
-        let y = (y + WORLD_SIZE as i32) as u32 % WORLD_SIZE;
-        let index = y * WORLD_SIZE + x;
+        let world_size = self.world_size;
+        let x = (x + world_size as i32) as u32 % world_size;
+        let y = (y + world_size as i32) as u32 % world_size;
+        let index = y * world_size + x;
         self.data[index as usize]
     }
 
@@ -198,19 +298,9 @@ impl Scorer {
         score
     }
 
-    fn hue_part(hue: f32) -> f32 {
-        let hue = hue * 3.0;
-        let hue = (hue + 3.0) % 3.0;
-        if hue < 1.0 {
-            hue
-        } else if hue > 2.0 {
-            3.0 - hue
-        } else {
-            1.0
-        }
-    }
-
-    pub fn create_gif(&self, densities: &[f32], filename: &str) {
+    /// Frames are indexed by cell value, so `colors` from `Palette::colors` doubles as the GIF
+    /// palette.
+    pub fn create_gif(&self, densities: &[f32], colors: &[Color], filename: &Path) {
         let mut period_pool: Vec<_> = (0..densities.len())
             .filter(|period| densities[*period] > 0.01)
             .collect();
@@ -230,12 +320,13 @@ impl Scorer {
             }
         }
         println!("{:?}", periods);
+        let world_size = self.snapshots[0].world_size as usize;
         let mut positions = Vec::new();
         while positions.len() < periods.len() {
             let period = periods[positions.len()];
-            let x: usize = rand::random::<usize>() % WORLD_SIZE as usize;
-            let y: usize = rand::random::<usize>() % WORLD_SIZE as usize;
-            let position = (y * WORLD_SIZE as usize) + x;
+            let x: usize = rand::random::<usize>() % world_size;
+            let y: usize = rand::random::<usize>() % world_size;
+            let position = (y * world_size) + x;
             if period == 1 && self.snapshots[0].data[position] == 0 {
                 continue;
             } else if period > 1 {
@@ -265,25 +356,7 @@ impl Scorer {
             }
             frames.push(frame.into_gif_frame());
         }
-        let mut palette = Vec::new();
-        palette.append(&mut vec![0x0, 0x0, 0x0]);
-        palette.append(&mut vec![0xFF, 0xFF, 0xFF]);
-        palette.append(&mut vec![0xFF, 0xFF, 0x0]);
-        let mut hue = 0.5;
-        let mut step = 1.0;
-        while palette.len() < 256 * 3 {
-            let r = Self::hue_part(hue + 0.66);
-            let g = Self::hue_part(hue + 0.33);
-            let b = Self::hue_part(hue);
-            palette.push((r * 255.9) as u8);
-            palette.push((g * 255.9) as u8);
-            palette.push((b * 255.9) as u8);
-            hue += step;
-            if hue >= 0.9999 {
-                step /= 2.0;
-                hue = step / 2.0;
-            }
-        }
+        let palette: Vec<u8> = colors.iter().flatten().copied().collect();
         let mut file = File::create(filename).unwrap();
         let mut encoder =
             Encoder::new(&mut file, frames[0].width, frames[0].width, &palette[..]).unwrap();
@@ -339,3 +412,63 @@ impl DisplayFrame {
         res
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(density: f32) -> Stats {
+        Stats {
+            population_density: density,
+            total_cells: 4,
+            value_counts: vec![2, 1, 1],
+        }
+    }
+
+    fn generations(history: &History) -> Vec<u64> {
+        history
+            .samples()
+            .iter()
+            .map(|sample| sample.generation)
+            .collect()
+    }
+
+    #[test]
+    fn history_respects_the_min_spacing() {
+        let mut history = History::new();
+        for generation in 0..1000 {
+            history.record(generation, 256, || stats(0.5));
+        }
+        assert_eq!(generations(&history), vec![0, 256, 512, 768]);
+    }
+
+    #[test]
+    fn history_starts_over_after_a_reset() {
+        let mut history = History::new();
+        for generation in 0..10 {
+            history.record(generation, 1, || stats(0.5));
+        }
+        history.record(3, 1, || stats(0.25));
+        assert_eq!(generations(&history), vec![3]);
+    }
+
+    #[test]
+    fn long_histories_are_thinned() {
+        let mut history = History::new();
+        for generation in 0..5000 {
+            history.record(generation, 1, || stats(0.5));
+        }
+        assert!(history.samples().len() <= MAX_HISTORY_SAMPLES);
+        assert_eq!(history.samples()[1].generation, 4);
+    }
+
+    #[test]
+    fn history_csv_has_a_column_per_value() {
+        let mut history = History::new();
+        history.record(7, 1, || stats(0.5));
+        assert_eq!(
+            history.to_csv(),
+            "generation,population_density,value_0,value_1,value_2\n7,0.5,2,1,1\n"
+        );
+    }
+}